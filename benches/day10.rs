@@ -0,0 +1,86 @@
+use std::{collections::HashSet, hint::black_box, thread, time::Instant};
+
+use adventofcode2019::day10;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+const SIZE: usize = 200;
+const DENSITY: f64 = 0.5;
+const SAMPLE: usize = 200;
+
+fn generate() -> String {
+    let mut rng = StdRng::seed_from_u64(10);
+    (0..SIZE)
+        .map(|_| {
+            (0..SIZE)
+                .map(|_| if rng.gen_bool(DENSITY) { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+fn sorted_hash_set(asteroids: &[(usize, usize)], x: usize, y: usize) -> usize {
+    let mut diffs = asteroids
+        .iter()
+        .map(|&(a_x, a_y)| {
+            (
+                x.abs_diff(a_x) + y.abs_diff(a_y),
+                a_x as i64 - x as i64,
+                a_y as i64 - y as i64,
+            )
+        })
+        .collect::<Vec<_>>();
+    diffs.sort_unstable();
+    let mut visited = HashSet::new();
+    diffs
+        .iter()
+        .skip(1)
+        .filter(|&&(_, dx, dy)| {
+            let gcd = gcd(dx.abs(), dy.abs());
+            visited.insert((dx / gcd, dy / gcd))
+        })
+        .count()
+}
+
+fn main() {
+    let asteroids = day10::asteroids(&generate());
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    println!(
+        "{}x{} map, {} asteroids, {} threads",
+        SIZE,
+        SIZE,
+        asteroids.len(),
+        threads
+    );
+
+    let start = Instant::now();
+    let expected = asteroids[..SAMPLE]
+        .iter()
+        .map(|&(x, y)| black_box(sorted_hash_set(&asteroids, x, y)))
+        .collect::<Vec<_>>();
+    let elapsed = start.elapsed();
+    println!(
+        "  sort + HashSet  {:>12?} for {} stations (~{:?} for all)",
+        elapsed,
+        SAMPLE,
+        elapsed * (asteroids.len() / SAMPLE) as u32
+    );
+
+    let mut runs = vec![1, threads];
+    runs.dedup();
+    for threads in runs {
+        let start = Instant::now();
+        let counts = black_box(day10::visibility(&asteroids, threads));
+        println!(
+            "  integer keys x{:<3} {:>12?} for all stations, best {}",
+            threads,
+            start.elapsed(),
+            counts.iter().max().unwrap()
+        );
+        assert_eq!(counts[..SAMPLE], expected);
+    }
+}
@@ -0,0 +1,99 @@
+use std::{collections::HashMap, hint::black_box, time::Instant};
+
+use adventofcode2019::day14::{Planner, Reactions};
+
+const INPUTS: [(&str, &str); 3] = [
+    (
+        "13312",
+        "157 ORE => 5 NZVS
+        165 ORE => 6 DCFZ
+        44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
+        12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
+        179 ORE => 7 PSHF
+        177 ORE => 5 HKGWZ
+        7 DCFZ, 7 PSHF => 2 XJWVT
+        165 ORE => 2 GPVTF
+        3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT",
+    ),
+    (
+        "180697",
+        "2 VPVL, 7 FWMGM, 2 CXFTF, 11 MNCFX => 1 STKFG
+        17 NVRVD, 3 JNWZP => 8 VPVL
+        53 STKFG, 6 MNCFX, 46 VJHF, 81 HVMC, 68 CXFTF, 25 GNMV => 1 FUEL
+        22 VJHF, 37 MNCFX => 5 FWMGM
+        139 ORE => 4 NVRVD
+        144 ORE => 7 JNWZP
+        5 MNCFX, 7 RFSQX, 2 FWMGM, 2 VPVL, 19 CXFTF => 3 HVMC
+        5 VJHF, 7 MNCFX, 9 VPVL, 37 CXFTF => 6 GNMV
+        145 ORE => 6 MNCFX
+        1 NVRVD => 8 CXFTF
+        1 VJHF, 6 MNCFX => 4 RFSQX
+        176 ORE => 6 VJHF",
+    ),
+    (
+        "2210736",
+        "171 ORE => 8 CNZTR
+        7 ZLQW, 3 BMBT, 9 XCVML, 26 XMNCP, 1 WPTQ, 2 MZWV, 1 RJRHP => 4 PLWSL
+        114 ORE => 4 BHXH
+        14 VRPVC => 6 BMBT
+        6 BHXH, 18 KTJDG, 12 WPTQ, 7 PLWSL, 31 FHTLT, 37 ZDVW => 1 FUEL
+        6 WPTQ, 2 BMBT, 8 ZLQW, 18 KTJDG, 1 XMNCP, 6 MZWV, 1 RJRHP => 6 FHTLT
+        15 XDBXC, 2 LTCX, 1 VRPVC => 6 ZLQW
+        13 WPTQ, 10 LTCX, 3 RJRHP, 14 XMNCP, 2 MZWV, 1 ZLQW => 1 ZDVW
+        5 BMBT => 4 WPTQ
+        189 ORE => 9 KTJDG
+        1 MZWV, 17 XDBXC, 3 XCVML => 2 XMNCP
+        12 VRPVC, 27 CNZTR => 2 XDBXC
+        15 KTJDG, 12 BHXH => 5 XCVML
+        3 BHXH, 2 VRPVC => 7 MZWV
+        121 ORE => 7 VRPVC
+        7 XCVML => 6 RJRHP
+        5 BHXH, 4 VRPVC => 5 LTCX",
+    ),
+];
+
+const ORE: i64 = 1000000000000;
+const RUNS: u32 = 20;
+
+fn binary_search(planner: &Planner) -> i64 {
+    let fits = |amount| planner.plan("FUEL", amount).raw_amount("ORE") <= ORE;
+    let (mut low, mut high) = (0, 1);
+    while fits(high) {
+        low = high;
+        high *= 2;
+    }
+    while low < high - 1 {
+        let mid = (low + high) / 2;
+        if fits(mid) {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+fn bench(name: &str, f: impl Fn() -> i64) -> i64 {
+    let start = Instant::now();
+    let mut result = 0;
+    for _ in 0..RUNS {
+        result = black_box(f());
+    }
+    let elapsed = start.elapsed() / RUNS;
+    println!("  {:<14} {:>12?}  -> {}", name, elapsed, result);
+    result
+}
+
+fn main() {
+    let budget = HashMap::from([("ORE", ORE)]);
+    for (name, input) in INPUTS {
+        println!("{} ORE sample:", name);
+        let planner = Planner::parse(input).unwrap();
+        let reactions = Reactions::parse(input).unwrap();
+        let old = bench("binary search", || binary_search(&planner));
+        let new = bench("reactions", || {
+            reactions.max_product(&budget, "FUEL").unwrap()
+        });
+        assert_eq!(old, new);
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    hint::black_box,
+    thread,
+    time::{Duration, Instant},
+};
+
+use adventofcode2019::day16::{Method, Transform};
+
+const SIGNAL: &str = "03036732577212944063491565474664";
+const REPEAT: usize = 10000;
+const PHASES: usize = 100;
+const RUNS: u32 = 3;
+
+fn signal() -> Vec<i64> {
+    SIGNAL
+        .bytes()
+        .map(|ch| (ch - b'0') as i64)
+        .cycle()
+        .take(SIGNAL.len() * REPEAT)
+        .collect()
+}
+
+fn scalar_suffix(offset: usize) -> Vec<i64> {
+    let mut values = signal().split_off(offset);
+    for _ in 0..PHASES {
+        let mut new_values = vec![0; values.len()];
+        let mut sum = 0;
+        for i in (0..values.len()).rev() {
+            sum = (sum + values[i]) % 10;
+            new_values[i] = sum;
+        }
+        values = new_values;
+    }
+    values.truncate(8);
+    values
+}
+
+fn scalar_prefix() -> Vec<i64> {
+    let pattern = [0, 1, 0, -1];
+    let mut values = signal();
+    let len = values.len();
+    for _ in 0..PHASES {
+        let mut prefix = vec![0; len + 1];
+        for (i, value) in values.iter().enumerate() {
+            prefix[i + 1] = prefix[i] + value;
+        }
+        values = (0..len)
+            .map(|i| {
+                let block = i + 1;
+                let mut sum: i64 = 0;
+                let mut run = 1;
+                while run * block <= len {
+                    let factor = pattern[run % 4];
+                    if factor != 0 {
+                        let to = ((run + 1) * block - 1).min(len);
+                        sum += factor * (prefix[to] - prefix[run * block - 1]);
+                    }
+                    run += 1;
+                }
+                sum.abs() % 10
+            })
+            .collect();
+    }
+    values.truncate(8);
+    values
+}
+
+fn bench(name: &str, f: impl Fn() -> Vec<i64>) -> Vec<i64> {
+    let mut best = Duration::MAX;
+    let mut result = vec![];
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        result = black_box(f());
+        best = best.min(start.elapsed());
+    }
+    println!("  {:<20} {:>12?}  -> {:?}", name, best, result);
+    result
+}
+
+fn kernel(transform: Transform) -> Vec<i64> {
+    transform
+        .with_repeat(REPEAT)
+        .with_phases(PHASES)
+        .run(SIGNAL)
+        .into_iter()
+        .map(i64::from)
+        .collect()
+}
+
+fn main() {
+    let threads = thread::available_parallelism().map_or(4, |n| n.get());
+    let offset = 303673;
+
+    println!("suffix sums, offset {}:", offset);
+    let expected = bench("scalar i64", || scalar_suffix(offset));
+    let result = bench("u8 kernel", || {
+        kernel(Transform::default().with_offset(offset))
+    });
+    assert_eq!(result, expected);
+
+    println!("prefix sums, offset 0:");
+    let expected = bench("scalar i64", scalar_prefix);
+    let result = bench("u8 kernel", || {
+        kernel(Transform::default().with_method(Method::PrefixSum))
+    });
+    assert_eq!(result, expected);
+    let result = bench(&format!("u8 kernel x{}", threads), || {
+        kernel(
+            Transform::default()
+                .with_method(Method::PrefixSum)
+                .with_threads(threads),
+        )
+    });
+    assert_eq!(result, expected);
+}
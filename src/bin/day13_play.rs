@@ -0,0 +1,123 @@
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use adventofcode2019::day13::{self, Arcade};
+
+const TICK: Duration = Duration::from_millis(150);
+const SAVE_FILE: &str = "day13.sav";
+
+enum Key {
+    Left,
+    Right,
+    Pause,
+    Save,
+    Load,
+    Quit,
+}
+
+fn read_keys(sender: mpsc::Sender<Key>) {
+    let mut bytes = io::stdin().lock().bytes().map_while(Result::ok);
+    while let Some(byte) = bytes.next() {
+        let key = match byte {
+            0x1b => match (bytes.next(), bytes.next()) {
+                (Some(b'['), Some(b'D')) => Key::Left,
+                (Some(b'['), Some(b'C')) => Key::Right,
+                _ => continue,
+            },
+            b'p' | b' ' => Key::Pause,
+            b's' => Key::Save,
+            b'l' => Key::Load,
+            b'q' | 0x03 => Key::Quit,
+            _ => continue,
+        };
+        if sender.send(key).is_err() {
+            break;
+        }
+    }
+}
+
+fn stty(args: &[&str]) {
+    let _ = Command::new("stty")
+        .args(args)
+        .stderr(Stdio::null())
+        .status();
+}
+
+fn draw(arcade: &Arcade, status: &str) {
+    let mut stdout = io::stdout();
+    let _ = write!(
+        stdout,
+        "\x1b[H\x1b[2JScore: {}  Blocks: {}  {}\r\n\r\n{}\r\n\r\n\
+         <-/-> move  p pause  s save  l load  q quit\r\n",
+        arcade.score(),
+        arcade.blocks(),
+        status,
+        arcade.render().replace('\n', "\r\n"),
+    );
+    let _ = stdout.flush();
+}
+
+fn main() {
+    let input = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path).unwrap(),
+        None => adventofcode2019::read_input(13),
+    };
+    let mut values = day13::parse(&input);
+    values[0] = 2;
+    let mut arcade = Arcade::new(values);
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || read_keys(sender));
+    stty(&["raw", "-echo"]);
+    print!("\x1b[?25l");
+
+    let mut joystick = 0;
+    let mut paused = false;
+    let mut status = String::new();
+    let mut deadline = Instant::now() + TICK;
+    while !arcade.is_halted() {
+        draw(&arcade, if paused { "PAUSED" } else { &status });
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Key::Left) => joystick = -1,
+            Ok(Key::Right) => joystick = 1,
+            Ok(Key::Pause) => paused = !paused,
+            Ok(Key::Save) => {
+                status = match fs::write(SAVE_FILE, arcade.save()) {
+                    Ok(()) => format!("saved to {}", SAVE_FILE),
+                    Err(err) => format!("save failed: {}", err),
+                };
+            }
+            Ok(Key::Load) => {
+                match fs::read_to_string(SAVE_FILE)
+                    .ok()
+                    .and_then(|data| Arcade::restore(&data))
+                {
+                    Some(restored) => {
+                        arcade = restored;
+                        status = format!("loaded {}", SAVE_FILE);
+                    }
+                    None => status = format!("could not load {}", SAVE_FILE),
+                }
+            }
+            Ok(Key::Quit) | Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                if !paused {
+                    arcade.step(joystick);
+                    joystick = 0;
+                }
+                deadline = Instant::now() + TICK;
+            }
+        }
+    }
+
+    draw(&arcade, "GAME OVER");
+    print!("\x1b[?25h");
+    stty(&["-raw", "echo"]);
+    println!("Final score: {}", arcade.score());
+}
@@ -1,51 +1,41 @@
-use std::collections::{HashMap, HashSet};
+use std::{collections::HashMap, thread};
 
 use itertools::Itertools;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target {
+    pub x: usize,
+    pub y: usize,
+    pub rotation: usize,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 struct Asteroid {
     x: i64,
     y: i64,
-    n: i64,
-    value: i64,
+    n: usize,
+    position: (usize, usize),
 }
 
 impl Asteroid {
-    fn quadrant(&self) -> usize {
-        match (self.x >= 0, self.y >= 0) {
-            (true, true) => 1,
-            (true, false) => 0,
-            (false, true) => 2,
-            (false, false) => 3,
+    fn half(&self) -> usize {
+        if self.x > 0 || (self.x == 0 && self.y < 0) {
+            0
+        } else {
+            1
         }
     }
 }
 
 impl Ord for Asteroid {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        if self.n != other.n {
-            return self.n.cmp(&other.n);
-        }
-
-        if self.quadrant() != other.quadrant() {
-            return self.quadrant().cmp(&other.quadrant());
-        }
-
-        match self.quadrant() {
-            0 => ((self.x.abs() as f64) / (self.y.abs() as f64))
-                .partial_cmp(&((other.x.abs() as f64) / (other.y.abs() as f64)))
-                .unwrap(),
-            1 => ((self.y.abs() as f64) / (self.x.abs() as f64))
-                .partial_cmp(&((other.y.abs() as f64) / (other.x.abs() as f64)))
-                .unwrap(),
-            2 => ((self.x.abs() as f64) / (self.y.abs() as f64))
-                .partial_cmp(&((other.x.abs() as f64) / (other.y.abs() as f64)))
-                .unwrap(),
-            3 => ((self.y.abs() as f64) / (self.x.abs() as f64))
-                .partial_cmp(&((other.y.abs() as f64) / (other.x.abs() as f64)))
-                .unwrap(),
-            _ => panic!(""),
-        }
+        self.n
+            .cmp(&other.n)
+            .then(self.half().cmp(&other.half()))
+            .then_with(|| {
+                let cross = self.x as i128 * other.y as i128 - self.y as i128 * other.x as i128;
+                0.cmp(&cross)
+            })
     }
 }
 
@@ -55,7 +45,7 @@ impl PartialOrd for Asteroid {
     }
 }
 
-fn parse(input: &str) -> Vec<Vec<u8>> {
+pub fn parse(input: &str) -> Vec<Vec<u8>> {
     input
         .trim()
         .lines()
@@ -82,38 +72,103 @@ fn gcd(mut a: i64, mut b: i64) -> i64 {
     }
 }
 
-fn find_asteroids(asteroids: &[(usize, usize)], x: usize, y: usize) -> i64 {
-    let mut visited: HashSet<(i64, i64)> = HashSet::new();
-    let mut diffs = asteroids
-        .iter()
-        .map(|&(a_x, a_y)| {
-            (
-                x.abs_diff(a_x) + y.abs_diff(a_y),
-                a_x as i64 - x as i64,
-                a_y as i64 - y as i64,
-            )
-        })
-        .collect_vec();
-    diffs.sort_unstable();
-    diffs
+struct Directions {
+    width: i64,
+    height: i64,
+    keys: Vec<u32>,
+}
+
+impl Directions {
+    fn new(width: usize, height: usize) -> Self {
+        let (width, height) = (width as i64, height as i64);
+        let index = |dx: i64, dy: i64| ((dx + width) * (2 * height + 1) + dy + height) as u32;
+        let keys = (-width..=width)
+            .flat_map(|dx| (-height..=height).map(move |dy| (dx, dy)))
+            .map(|(dx, dy)| match gcd(dx.abs(), dy.abs()) {
+                0 => index(0, 0),
+                gcd => index(dx / gcd, dy / gcd),
+            })
+            .collect();
+        Self {
+            width,
+            height,
+            keys,
+        }
+    }
+
+    fn key(&self, dx: i64, dy: i64) -> u32 {
+        self.keys[((dx + self.width) * (2 * self.height + 1) + dy + self.height) as usize]
+    }
+}
+
+fn count_visible(
+    asteroids: &[(usize, usize)],
+    stations: &[(usize, usize)],
+    directions: &Directions,
+) -> Vec<usize> {
+    let mut seen = vec![0u32; directions.keys.len()];
+    let mut stamp = 0;
+    stations
         .iter()
-        .skip(1)
-        .map(|&(_, dx, dy)| {
-            let gcd = gcd(dx.abs(), dy.abs());
-            if !visited.contains(&(dx / gcd, dy / gcd)) {
-                visited.insert((dx / gcd, dy / gcd));
-                1
-            } else {
-                0
+        .map(|&(x, y)| {
+            stamp += 1;
+            seen[directions.key(0, 0) as usize] = stamp;
+            let mut count = 0;
+            for &(a_x, a_y) in asteroids {
+                let key = directions.key(a_x as i64 - x as i64, a_y as i64 - y as i64) as usize;
+                if seen[key] != stamp {
+                    seen[key] = stamp;
+                    count += 1;
+                }
             }
+            count
         })
-        .sum()
+        .collect()
 }
 
-fn nth_asteroid(asteroids: &[(usize, usize)], x: usize, y: usize, index: usize) -> i64 {
-    let mut visited: HashMap<(i64, i64), i64> = HashMap::new();
+pub fn visibility(asteroids: &[(usize, usize)], threads: usize) -> Vec<usize> {
+    let (width, height) = asteroids
+        .iter()
+        .fold((0, 0), |(w, h), &(x, y)| (w.max(x + 1), h.max(y + 1)));
+    let directions = Directions::new(width, height);
+    let directions = &directions;
+    let chunk = asteroids.len().div_ceil(threads.max(1)).max(1);
+    thread::scope(|scope| {
+        asteroids
+            .chunks(chunk)
+            .map(|stations| scope.spawn(move || count_visible(asteroids, stations, directions)))
+            .collect_vec()
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+fn threads() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+pub fn asteroids(input: &str) -> Vec<(usize, usize)> {
+    AsteroidField::new(&parse(input)).asteroids
+}
+
+fn best_station(asteroids: &[(usize, usize)]) -> (usize, (usize, usize)) {
+    visibility(asteroids, threads())
+        .into_iter()
+        .zip(asteroids.iter().copied())
+        .max()
+        .unwrap()
+}
+
+pub fn vaporize(
+    asteroids: &[(usize, usize)],
+    x: usize,
+    y: usize,
+) -> impl Iterator<Item = Target> + use<> {
+    let mut visited: HashMap<(i64, i64), usize> = HashMap::new();
     let mut diffs = asteroids
         .iter()
+        .filter(|&&position| position != (x, y))
         .map(|&(a_x, a_y)| {
             (
                 x.abs_diff(a_x) + y.abs_diff(a_y),
@@ -125,57 +180,115 @@ fn nth_asteroid(asteroids: &[(usize, usize)], x: usize, y: usize, index: usize)
     diffs.sort_unstable();
     diffs
         .iter()
-        .skip(1)
         .map(|&(_, dx, dy)| {
             let gcd = gcd(dx.abs(), dy.abs());
             let entry = visited.entry((dx / gcd, dy / gcd)).or_default();
             *entry += 1;
             Asteroid {
-                x: dx,
-                y: dy,
+                x: dx / gcd,
+                y: dy / gcd,
                 n: *entry,
-                value: 100 * (x as i64 + dx) + y as i64 + dy,
+                position: ((x as i64 + dx) as usize, (y as i64 + dy) as usize),
             }
         })
         .sorted_unstable()
+        .map(|asteroid| Target {
+            x: asteroid.position.0,
+            y: asteroid.position.1,
+            rotation: asteroid.n,
+        })
+}
+
+#[derive(Debug, Clone)]
+pub struct AsteroidField {
+    width: usize,
+    height: usize,
+    asteroids: Vec<(usize, usize)>,
+}
+
+impl AsteroidField {
+    pub fn new(field: &[Vec<u8>]) -> Self {
+        let asteroids = (0..field.len())
+            .flat_map(|y| (0..field[y].len()).map(move |x| (x, y)))
+            .filter(|&(x, y)| field[y][x] == b'#')
+            .collect();
+        Self {
+            width: field.iter().map(|row| row.len()).max().unwrap_or(0),
+            height: field.len(),
+            asteroids,
+        }
+    }
+
+    pub fn asteroids(&self) -> &[(usize, usize)] {
+        &self.asteroids
+    }
+
+    pub fn contains(&self, position: (usize, usize)) -> bool {
+        self.asteroids
+            .binary_search_by_key(&(position.1, position.0), |&(x, y)| (y, x))
+            .is_ok()
+    }
+
+    pub fn blocker(&self, from: (usize, usize), to: (usize, usize)) -> Option<(usize, usize)> {
+        let dx = to.0 as i64 - from.0 as i64;
+        let dy = to.1 as i64 - from.1 as i64;
+        let steps = gcd(dx.abs(), dy.abs());
+        (1..steps)
+            .map(|i| {
+                (
+                    (from.0 as i64 + dx / steps * i) as usize,
+                    (from.1 as i64 + dy / steps * i) as usize,
+                )
+            })
+            .find(|&position| self.contains(position))
+    }
+
+    pub fn can_see(&self, from: (usize, usize), to: (usize, usize)) -> bool {
+        from != to && self.contains(from) && self.contains(to) && self.blocker(from, to).is_none()
+    }
+
+    pub fn heatmap(&self) -> Vec<Vec<Option<usize>>> {
+        let mut grid = vec![vec![None; self.width]; self.height];
+        for (&(x, y), count) in self
+            .asteroids
+            .iter()
+            .zip(visibility(&self.asteroids, threads()))
+        {
+            grid[y][x] = Some(count);
+        }
+        grid
+    }
+
+    pub fn visible_by_direction(
+        &self,
+        station: (usize, usize),
+    ) -> Vec<((i64, i64), (usize, usize))> {
+        vaporize(&self.asteroids, station.0, station.1)
+            .take_while(|target| target.rotation == 1)
+            .map(|target| {
+                let dx = target.x as i64 - station.0 as i64;
+                let dy = target.y as i64 - station.1 as i64;
+                let gcd = gcd(dx.abs(), dy.abs());
+                ((dx / gcd, dy / gcd), (target.x, target.y))
+            })
+            .collect()
+    }
+}
+
+fn nth_asteroid(asteroids: &[(usize, usize)], x: usize, y: usize, index: usize) -> i64 {
+    vaporize(asteroids, x, y)
         .nth(index)
-        .map(|a| a.value)
+        .map(|target| 100 * target.x as i64 + target.y as i64)
         .unwrap()
 }
 
 pub fn part1(input: &str) -> i64 {
-    let field = parse(input);
-    let asteroids = (0..field.len())
-        .flat_map(|i| {
-            (0..field[0].len())
-                .map(|j| (j, i))
-                .filter(|&(j, i)| field[i][j] == b'#')
-                .collect_vec()
-        })
-        .collect_vec();
-    asteroids
-        .iter()
-        .map(|&(x, y)| find_asteroids(&asteroids, x, y))
-        .max()
-        .unwrap()
+    best_station(&asteroids(input)).0 as i64
 }
 
 pub fn part2(input: &str) -> i64 {
-    let field = parse(input);
-    let asteroids = (0..field.len())
-        .flat_map(|i| {
-            (0..field[0].len())
-                .map(|j| (j, i))
-                .filter(|&(j, i)| field[i][j] == b'#')
-                .collect_vec()
-        })
-        .collect_vec();
-    let (_, x, y) = asteroids
-        .iter()
-        .map(|&(x, y)| (find_asteroids(&asteroids, x, y), x, y))
-        .max()
-        .unwrap();
-
+    let asteroids = asteroids(input);
+    let (_, (x, y)) = best_station(&asteroids);
     nth_asteroid(&asteroids, x, y, 199)
 }
 
@@ -266,4 +379,118 @@ mod tests {
     fn test_day10_part2() {
         assert_eq!(part2(INPUT5), 802);
     }
+
+    #[test]
+    fn test_day10_vaporize() {
+        let asteroids = asteroids(INPUT5);
+        let order = vaporize(&asteroids, 11, 13).collect_vec();
+        assert_eq!(order.len(), asteroids.len() - 1);
+        let at = |i: usize| (order[i - 1].x, order[i - 1].y);
+        assert_eq!(at(1), (11, 12));
+        assert_eq!(at(2), (12, 1));
+        assert_eq!(at(3), (12, 2));
+        assert_eq!(at(10), (12, 8));
+        assert_eq!(at(20), (16, 0));
+        assert_eq!(at(50), (16, 9));
+        assert_eq!(at(100), (10, 16));
+        assert_eq!(at(199), (9, 6));
+        assert_eq!(at(200), (8, 2));
+        assert_eq!(at(201), (10, 9));
+        assert_eq!(at(299), (11, 1));
+        assert!(order.windows(2).all(|w| w[0].rotation <= w[1].rotation));
+        assert_eq!(order[0].rotation, 1);
+
+        let cross = [(5, 0), (5, 10), (0, 5), (10, 5), (5, 2), (2, 5), (9, 9)];
+        let order = vaporize(&cross, 5, 5)
+            .map(|target| (target.x, target.y, target.rotation))
+            .collect_vec();
+        assert_eq!(
+            order,
+            vec![
+                (5, 2, 1),
+                (10, 5, 1),
+                (9, 9, 1),
+                (5, 10, 1),
+                (2, 5, 1),
+                (5, 0, 2),
+                (0, 5, 2)
+            ]
+        );
+
+        let far = [
+            (0, 0),
+            (3_000_000_000, 0),
+            (1_000_000_000, 1),
+            (2_999_999_999, 1),
+        ];
+        let order = vaporize(&far, 0, 2_000_000_000)
+            .map(|target| (target.x, target.y))
+            .collect_vec();
+        assert_eq!(
+            order,
+            vec![
+                (0, 0),
+                (1_000_000_000, 1),
+                (3_000_000_000, 0),
+                (2_999_999_999, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day10_visibility() {
+        let asteroids = asteroids(INPUT1);
+        assert_eq!(
+            asteroids,
+            vec![
+                (1, 0),
+                (4, 0),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (3, 2),
+                (4, 2),
+                (4, 3),
+                (3, 4),
+                (4, 4)
+            ]
+        );
+        let expected = vec![7, 7, 6, 7, 7, 7, 5, 7, 8, 7];
+        for threads in [1, 3, 16] {
+            assert_eq!(visibility(&asteroids, threads), expected);
+        }
+        assert_eq!(visibility(&[], 4), vec![]);
+    }
+
+    #[test]
+    fn test_day10_field() {
+        let field = AsteroidField::new(&parse(INPUT1));
+        assert!(field.contains((3, 4)) && !field.contains((0, 0)));
+        assert_eq!(field.blocker((1, 0), (3, 4)), Some((2, 2)));
+        assert_eq!(field.blocker((0, 2), (4, 2)), Some((1, 2)));
+        assert_eq!(field.blocker((3, 4), (4, 0)), None);
+        assert!(!field.can_see((1, 0), (3, 4)));
+        assert!(field.can_see((3, 4), (4, 0)));
+        assert!(!field.can_see((3, 4), (3, 4)));
+        assert!(!field.can_see((3, 4), (0, 0)));
+
+        let heatmap = field.heatmap();
+        assert_eq!(
+            heatmap[2],
+            vec![Some(6), Some(7), Some(7), Some(7), Some(5)]
+        );
+        assert_eq!(heatmap[1], vec![None; 5]);
+        assert_eq!(heatmap[4][3], Some(8));
+
+        let visible = field.visible_by_direction((3, 4));
+        assert_eq!(visible.len(), 8);
+        assert_eq!(visible[0], ((0, -1), (3, 2)));
+        assert_eq!(visible[1], ((1, -4), (4, 0)));
+        assert!(
+            visible
+                .iter()
+                .all(|&(_, target)| field.can_see((3, 4), target))
+        );
+        assert!(!visible.iter().any(|&(_, target)| target == (1, 0)));
+    }
 }
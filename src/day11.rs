@@ -2,6 +2,8 @@ use std::collections::{HashMap, VecDeque};
 
 use itertools::Itertools;
 
+use crate::ocr;
+
 #[derive(Debug)]
 enum IntCodeState {
     Created,
@@ -152,66 +154,220 @@ fn parse(input: &str) -> Vec<i64> {
         .collect::<Vec<i64>>()
 }
 
-fn solve(input: &str, start: i64) -> (HashMap<(i64, i64), i64>, (i64, i64, i64, i64)) {
-    let values = parse(input);
-    let mut int_code = IntCode::new(values, VecDeque::new());
-    let mut dir = (0, 1);
-    let mut pos = (0, 0);
-    let mut points = HashMap::new();
-    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0, 0, 0, 0);
-    points.insert(pos, start);
-    while !matches!(int_code.state, IntCodeState::Halted) {
-        let color = if let Some(&color) = points.get(&pos) {
-            color
-        } else {
-            0
-        };
+pub trait Brain {
+    fn decide(&mut self, color: i64) -> Option<(i64, i64)>;
+}
 
-        int_code.add_input(color);
-        int_code.execute();
+impl<F: FnMut(i64) -> Option<(i64, i64)>> Brain for F {
+    fn decide(&mut self, color: i64) -> Option<(i64, i64)> {
+        self(color)
+    }
+}
 
-        let new_color = int_code.output[int_code.output.len() - 2];
-        let turn = int_code.output[int_code.output.len() - 1];
+pub struct IntCodeBrain {
+    int_code: IntCode,
+}
 
-        points.insert(pos, new_color);
-        if turn == 0 {
-            dir = (-dir.1, dir.0);
-        } else {
-            dir = (dir.1, -dir.0);
+impl IntCodeBrain {
+    pub fn new(program: Vec<i64>) -> Self {
+        Self {
+            int_code: IntCode::new(program, VecDeque::new()),
         }
-        pos = (pos.0 + dir.0, pos.1 + dir.1);
+    }
+}
 
-        min_x = min_x.min(pos.0);
-        max_x = max_x.max(pos.0);
-        min_y = min_y.min(pos.1);
-        max_y = max_y.max(pos.1);
+impl Brain for IntCodeBrain {
+    fn decide(&mut self, color: i64) -> Option<(i64, i64)> {
+        if matches!(self.int_code.state, IntCodeState::Halted) {
+            return None;
+        }
+        self.int_code.add_input(color);
+        self.int_code.execute();
+        match self.int_code.output.drain(..).collect_vec()[..] {
+            [paint, turn] => Some((paint, turn)),
+            _ => None,
+        }
     }
-    (points, (min_x, min_y, max_x, max_y))
 }
 
-pub fn part1(input: &str) -> i64 {
-    solve(input, 0).0.len() as i64
+pub struct PaintingRobot {
+    pos: (i64, i64),
+    heading: (i64, i64),
+    panels: HashMap<(i64, i64), i64>,
+    paint_counts: HashMap<(i64, i64), usize>,
+    bounds: (i64, i64, i64, i64),
 }
 
-pub fn part2(input: &str) -> String {
-    let (map, (min_x, min_y, max_x, max_y)) = solve(input, 1);
-    let mut chars = vec![vec![b' '; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
-    for (key, value) in map {
-        if value == 1 {
-            chars[(max_y - key.1) as usize][(key.0 - min_x) as usize] = b'#';
+impl PaintingRobot {
+    pub fn new(start: i64) -> Self {
+        Self {
+            pos: (0, 0),
+            heading: (0, 1),
+            panels: HashMap::from([((0, 0), start)]),
+            paint_counts: HashMap::new(),
+            bounds: (0, 0, 0, 0),
         }
     }
-    chars
-        .iter()
-        .map(|row| std::str::from_utf8(row).unwrap())
-        .join("\n")
+
+    pub fn step(&mut self, brain: &mut dyn Brain) -> bool {
+        let Some((paint, turn)) = brain.decide(self.color(self.pos)) else {
+            return false;
+        };
+
+        self.panels.insert(self.pos, paint);
+        *self.paint_counts.entry(self.pos).or_default() += 1;
+        let dir = self.heading;
+        self.heading = if turn == 0 {
+            (-dir.1, dir.0)
+        } else {
+            (dir.1, -dir.0)
+        };
+        self.pos = (self.pos.0 + self.heading.0, self.pos.1 + self.heading.1);
+
+        let (min_x, min_y, max_x, max_y) = &mut self.bounds;
+        *min_x = (*min_x).min(self.pos.0);
+        *max_x = (*max_x).max(self.pos.0);
+        *min_y = (*min_y).min(self.pos.1);
+        *max_y = (*max_y).max(self.pos.1);
+        true
+    }
+
+    pub fn run(&mut self, brain: &mut dyn Brain) {
+        while self.step(brain) {}
+    }
+
+    pub fn position(&self) -> (i64, i64) {
+        self.pos
+    }
+
+    pub fn heading(&self) -> (i64, i64) {
+        self.heading
+    }
+
+    pub fn color(&self, pos: (i64, i64)) -> i64 {
+        self.panels.get(&pos).copied().unwrap_or(0)
+    }
+
+    pub fn panels(&self) -> &HashMap<(i64, i64), i64> {
+        &self.panels
+    }
+
+    pub fn painted(&self) -> usize {
+        self.paint_counts.len()
+    }
+
+    pub fn painted_more_than_once(&self) -> usize {
+        self.paint_counts
+            .values()
+            .filter(|&&count| count > 1)
+            .count()
+    }
+
+    pub fn render(&self) -> String {
+        let (min_x, min_y, max_x, max_y) = self.bounds;
+        let mut chars =
+            vec![vec![b' '; (max_x - min_x + 1) as usize]; (max_y - min_y + 1) as usize];
+        for (key, &value) in &self.panels {
+            if value == 1 {
+                chars[(max_y - key.1) as usize][(key.0 - min_x) as usize] = b'#';
+            }
+        }
+        chars
+            .iter()
+            .map(|row| std::str::from_utf8(row).unwrap())
+            .join("\n")
+    }
+}
+
+fn solve(input: &str, start: i64) -> PaintingRobot {
+    let mut robot = PaintingRobot::new(start);
+    robot.run(&mut IntCodeBrain::new(parse(input)));
+    robot
+}
+
+pub fn part1(input: &str) -> i64 {
+    solve(input, 0).painted() as i64
+}
+
+pub fn part2(input: &str) -> Result<String, ocr::OcrError> {
+    ocr::recognize(&solve(input, 1).render())
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    const ROBOT_PAINTS: &str = "1011011";
+    const ROBOT_TURNS: &str = "0000100";
+    const FL_PAINTS: &str = "100111000001100110011100100011000110011101100110011000000011";
+    const FL_TURNS: &str = "000101011011011001101101000111010101100001011001110101011000";
+
+    fn program(paints: &str, turns: &str) -> String {
+        let scratch = 7 * paints.len() + 1;
+        paints
+            .chars()
+            .zip(turns.chars())
+            .map(|(paint, turn)| format!("3,{},104,{},104,{},", scratch, paint, turn))
+            .chain(["99".to_string()])
+            .collect()
+    }
+
+    #[test]
+    fn test_day11_part1() {
+        assert_eq!(part1(&program(ROBOT_PAINTS, ROBOT_TURNS)), 6);
+        assert_eq!(part1(&program(FL_PAINTS, FL_TURNS)), 47);
+    }
+
     #[test]
-    fn test_day11_part1() {}
+    fn test_day11_part2() {
+        assert_eq!(part2(&program(FL_PAINTS, FL_TURNS)), Ok("FL".to_string()));
+        assert_eq!(
+            part2(&program(ROBOT_PAINTS, ROBOT_TURNS)),
+            Err(ocr::OcrError::UnsupportedHeight(3))
+        );
+    }
 
     #[test]
-    fn test_day11_part2() {}
+    fn test_day11_robot() {
+        let mut script = ROBOT_PAINTS
+            .bytes()
+            .zip(ROBOT_TURNS.bytes())
+            .map(|(paint, turn)| ((paint - b'0') as i64, (turn - b'0') as i64));
+        let mut seen = vec![];
+        let mut brain = |color| {
+            seen.push(color);
+            script.next()
+        };
+        let mut robot = PaintingRobot::new(0);
+        robot.run(&mut brain);
+        assert_eq!(seen, [0, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(robot.painted(), 6);
+        assert_eq!(robot.painted_more_than_once(), 1);
+        assert_eq!(robot.position(), (0, 1));
+        assert_eq!(robot.heading(), (-1, 0));
+        assert_eq!(robot.render(), "  #\n  #\n## ");
+    }
+
+    #[test]
+    fn test_day11_intcode_brain() {
+        let mut brain = IntCodeBrain::new(vec![3, 100, 104, 1, 104, 0, 3, 100, 4, 100, 104, 1, 99]);
+        let mut robot = PaintingRobot::new(0);
+        assert!(robot.step(&mut brain));
+        assert_eq!((robot.position(), robot.color((0, 0))), ((-1, 0), 1));
+        assert!(robot.step(&mut brain));
+        assert_eq!(robot.position(), (-1, 1));
+        assert!(!robot.step(&mut brain));
+    }
+
+    #[test]
+    fn test_day11_ocr() {
+        let mut script = FL_PAINTS
+            .bytes()
+            .zip(FL_TURNS.bytes())
+            .map(|(paint, turn)| ((paint - b'0') as i64, (turn - b'0') as i64));
+        let mut robot = PaintingRobot::new(0);
+        robot.run(&mut |_| script.next());
+        assert_eq!(robot.painted(), 47);
+        assert_eq!(ocr::recognize(&robot.render()), Ok("FL".to_string()));
+    }
 }
@@ -1,29 +1,520 @@
-use itertools::Itertools;
+use std::{
+    fmt::Debug,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, Write},
+    ops::Range,
+    sync::Arc,
+};
+
 use regex::Regex;
 
-#[derive(Debug)]
-struct Moon {
-    pos: [i64; 3],
-    speed: [i64; 3],
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Axis {
+    pub positions: Vec<i64>,
+    pub velocities: Vec<i64>,
+}
+
+impl Axis {
+    fn new(positions: Vec<i64>) -> Self {
+        let velocities = vec![0; positions.len()];
+        Self {
+            positions,
+            velocities,
+        }
+    }
+
+    fn step(&mut self, law: &dyn ForceLaw, masses: &[i64]) {
+        let accelerations = law.accelerations(self, masses);
+        self.velocities
+            .iter_mut()
+            .zip(accelerations)
+            .for_each(|(v, a)| *v += a);
+        self.positions
+            .iter_mut()
+            .zip(&self.velocities)
+            .for_each(|(p, v)| *p += v);
+    }
+}
+
+pub trait ForceLaw: Debug {
+    fn accelerations(&self, axis: &Axis, masses: &[i64]) -> Vec<i64>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitGravity;
+
+impl ForceLaw for UnitGravity {
+    fn accelerations(&self, axis: &Axis, masses: &[i64]) -> Vec<i64> {
+        let positions = &axis.positions;
+        let mut order = (0..positions.len()).collect::<Vec<_>>();
+        order.sort_unstable_by_key(|&i| positions[i]);
+        let total = masses.iter().sum::<i64>();
+        let mut accelerations = vec![0; positions.len()];
+        let mut below = 0;
+        for group in order.chunk_by(|&i, &j| positions[i] == positions[j]) {
+            let mass = group.iter().map(|&i| masses[i]).sum::<i64>();
+            let above = total - below - mass;
+            for &i in group {
+                accelerations[i] = above - below;
+            }
+            below += mass;
+        }
+        accelerations
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct InverseDistance {
+    pub strength: i64,
+}
+
+impl ForceLaw for InverseDistance {
+    fn accelerations(&self, axis: &Axis, masses: &[i64]) -> Vec<i64> {
+        let positions = &axis.positions;
+        (0..positions.len())
+            .map(|i| {
+                (0..positions.len())
+                    .filter(|&j| positions[j] != positions[i])
+                    .map(|j| {
+                        let distance = positions[j] - positions[i];
+                        distance.signum() * masses[j] * (self.strength / distance.abs())
+                    })
+                    .sum::<i64>()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Damped<L> {
+    law: L,
+    divisor: i64,
 }
 
-impl Moon {
-    fn energy(&self) -> i64 {
-        self.pos.iter().map(|x| x.abs()).sum::<i64>()
-            * self.speed.iter().map(|x| x.abs()).sum::<i64>()
+impl<L: ForceLaw> Damped<L> {
+    pub fn new(law: L, divisor: i64) -> Self {
+        assert!(divisor != 0, "damping divisor must not be zero");
+        Self { law, divisor }
+    }
+}
+
+impl<L: ForceLaw> ForceLaw for Damped<L> {
+    fn accelerations(&self, axis: &Axis, masses: &[i64]) -> Vec<i64> {
+        self.law
+            .accelerations(axis, masses)
+            .into_iter()
+            .zip(&axis.velocities)
+            .map(|(a, v)| a - v / self.divisor)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct System {
+    names: Vec<String>,
+    axes: Vec<Axis>,
+    masses: Vec<i64>,
+    law: Arc<dyn ForceLaw + Send + Sync>,
+}
+
+impl System {
+    pub fn new(names: &[&str], positions: &[Vec<i64>]) -> Self {
+        assert!(
+            positions.iter().all(|body| body.len() == names.len()),
+            "every body needs one coordinate per axis"
+        );
+        Self {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            axes: (0..names.len())
+                .map(|k| Axis::new(positions.iter().map(|body| body[k]).collect()))
+                .collect(),
+            masses: vec![1; positions.len()],
+            law: Arc::new(UnitGravity),
+        }
+    }
+
+    pub fn with_law(mut self, law: impl ForceLaw + Send + Sync + 'static) -> Self {
+        self.law = Arc::new(law);
+        self
+    }
+
+    pub fn with_masses(mut self, masses: &[i64]) -> Self {
+        assert_eq!(masses.len(), self.bodies(), "every body needs a mass");
+        assert!(masses.iter().all(|&m| m > 0), "masses must be positive");
+        self.masses = masses.to_vec();
+        self
+    }
+
+    pub fn parse(input: &str) -> Self {
+        let body = Regex::new(r"<([^>]*)>").unwrap();
+        let coordinate = Regex::new(r"(\w+)\s*=\s*(-?\d+)").unwrap();
+        let mut names: Option<Vec<&str>> = None;
+        let mut positions = vec![];
+        for captures in body.captures_iter(input) {
+            let (_, [inner]) = captures.extract();
+            let (axes, values): (Vec<_>, Vec<_>) = coordinate
+                .captures_iter(inner)
+                .map(|captures| {
+                    let (_, [name, value]) = captures.extract();
+                    (name, value.parse::<i64>().unwrap())
+                })
+                .unzip();
+            match &names {
+                Some(names) => assert_eq!(names, &axes, "bodies must share the same axes"),
+                None => names = Some(axes),
+            }
+            positions.push(values);
+        }
+        Self::new(&names.unwrap_or_default(), &positions)
+    }
+
+    pub fn axis_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn axes(&self) -> &[Axis] {
+        &self.axes
+    }
+
+    pub fn dimensions(&self) -> usize {
+        self.axes.len()
+    }
+
+    pub fn bodies(&self) -> usize {
+        self.axes.first().map_or(0, |axis| axis.positions.len())
+    }
+
+    pub fn position(&self, body: usize) -> Vec<i64> {
+        self.axes.iter().map(|axis| axis.positions[body]).collect()
+    }
+
+    pub fn velocity(&self, body: usize) -> Vec<i64> {
+        self.axes.iter().map(|axis| axis.velocities[body]).collect()
+    }
+
+    pub fn masses(&self) -> &[i64] {
+        &self.masses
+    }
+
+    pub fn step(&mut self) {
+        for axis in &mut self.axes {
+            axis.step(self.law.as_ref(), &self.masses);
+        }
+    }
+
+    pub fn run(&mut self, steps: usize) {
+        (0..steps).for_each(|_| self.step());
+    }
+
+    pub fn states(&self) -> impl Iterator<Item = System> + use<> {
+        std::iter::successors(Some(self.clone()), |state| {
+            let mut next = state.clone();
+            next.step();
+            Some(next)
+        })
+    }
+
+    pub fn cycles(&self) -> (Vec<Cycle>, Cycle) {
+        let axes = self
+            .axes
+            .iter()
+            .map(|axis| {
+                find_cycle(axis, |axis| {
+                    let mut axis = axis.clone();
+                    axis.step(self.law.as_ref(), &self.masses);
+                    axis
+                })
+            })
+            .collect::<Vec<_>>();
+        let system = axes.iter().fold(
+            Cycle {
+                start: 0,
+                length: 1,
+            },
+            |acc, cycle| {
+                let length = acc.length as i64;
+                Cycle {
+                    start: acc.start.max(cycle.start),
+                    length: (length / gcd(length, cycle.length as i64)) as usize * cycle.length,
+                }
+            },
+        );
+        (axes, system)
+    }
+
+    pub fn energy(&self) -> i64 {
+        (0..self.bodies())
+            .map(|body| {
+                let potential = self.position(body).iter().map(|x| x.abs()).sum::<i64>();
+                let kinetic = self.velocity(body).iter().map(|x| x.abs()).sum::<i64>();
+                potential * kinetic * self.masses[body]
+            })
+            .sum()
+    }
+
+    pub fn momentum(&self) -> Vec<i64> {
+        self.axes
+            .iter()
+            .map(|axis| {
+                axis.velocities
+                    .iter()
+                    .zip(&self.masses)
+                    .map(|(v, m)| v * m)
+                    .sum()
+            })
+            .collect()
+    }
+
+    pub fn record(&self, steps: usize) -> Trajectory {
+        Trajectory {
+            names: self.names.clone(),
+            frames: self
+                .states()
+                .take(steps + 1)
+                .map(|state| state.axes)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    names: Vec<String>,
+    frames: Vec<Vec<Axis>>,
+}
+
+impl Trajectory {
+    pub fn axis_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn steps(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn bodies(&self) -> usize {
+        self.frames
+            .first()
+            .and_then(|axes| axes.first())
+            .map_or(0, |axis| axis.positions.len())
+    }
+
+    pub fn position(&self, step: usize, body: usize) -> Vec<i64> {
+        self.frames[step]
+            .iter()
+            .map(|axis| axis.positions[body])
+            .collect()
+    }
+
+    pub fn velocity(&self, step: usize, body: usize) -> Vec<i64> {
+        self.frames[step]
+            .iter()
+            .map(|axis| axis.velocities[body])
+            .collect()
     }
 
-    fn update(&mut self) {
-        self.pos.iter_mut().zip(self.speed).for_each(|(p, s)| {
-            *p += s;
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        let velocities = self.names.iter().map(|name| format!("v{}", name));
+        let header = ["step".to_string(), "body".to_string()]
+            .into_iter()
+            .chain(self.names.iter().cloned())
+            .chain(velocities)
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", header.join(","))?;
+        for step in 0..self.steps() {
+            for body in 0..self.bodies() {
+                let row = [step as i64, body as i64]
+                    .into_iter()
+                    .chain(self.position(step, body))
+                    .chain(self.velocity(step, body))
+                    .map(|value| value.to_string())
+                    .collect::<Vec<_>>();
+                writeln!(writer, "{}", row.join(","))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn sample(&self, range: &Range<usize>, every: usize) -> Vec<usize> {
+        (range.start..range.end.min(self.steps()))
+            .step_by(every.max(1))
+            .collect()
+    }
+
+    pub fn to_svg(
+        &self,
+        axis: usize,
+        range: Range<usize>,
+        every: usize,
+        cycle: Option<Cycle>,
+    ) -> String {
+        const WIDTH: f64 = 800.0;
+        const HEIGHT: f64 = 300.0;
+        const MARGIN: f64 = 40.0;
+        const COLORS: [&str; 6] = [
+            "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+        ];
+
+        let steps = self.sample(&range, every);
+        let values = steps
+            .iter()
+            .flat_map(|&step| self.frames[step][axis].positions.iter().copied());
+        let (low, high) = values.fold((i64::MAX, i64::MIN), |(low, high), value| {
+            (low.min(value), high.max(value))
         });
+        let (low, high) = if low > high { (0, 0) } else { (low, high) };
+        let (first, last) = (
+            steps.first().copied().unwrap_or(0),
+            steps.last().copied().unwrap_or(0),
+        );
+        let x = |step: usize| {
+            MARGIN + (step - first) as f64 * (WIDTH - 2.0 * MARGIN) / (last - first).max(1) as f64
+        };
+        let y = |value: i64| {
+            HEIGHT
+                - MARGIN
+                - (value - low) as f64 * (HEIGHT - 2.0 * MARGIN) / (high - low).max(1) as f64
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+            WIDTH, HEIGHT
+        );
+        svg += &format!(
+            "<text x=\"{}\" y=\"20\">{} (steps {}..={})</text>\n",
+            MARGIN, self.names[axis], first, last
+        );
+        svg += &format!(
+            "<line x1=\"{m}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"black\"/>\n",
+            m = MARGIN,
+            b = HEIGHT - MARGIN,
+            r = WIDTH - MARGIN
+        );
+        if let Some(cycle) = cycle {
+            svg += &format!(
+                "<text x=\"{}\" y=\"{}\">cycle start {}, length {}</text>\n",
+                MARGIN,
+                HEIGHT - 10.0,
+                cycle.start,
+                cycle.length
+            );
+            let marks = (cycle.start..=last)
+                .step_by(cycle.length.max(1))
+                .filter(|&step| step >= first);
+            for step in marks {
+                svg += &format!(
+                    "<line x1=\"{x:.1}\" y1=\"{}\" x2=\"{x:.1}\" y2=\"{}\" stroke=\"gray\" stroke-dasharray=\"4\"/>\n",
+                    MARGIN,
+                    HEIGHT - MARGIN,
+                    x = x(step)
+                );
+            }
+        }
+        for body in 0..self.bodies() {
+            let points = steps
+                .iter()
+                .map(|&step| {
+                    let value = self.frames[step][axis].positions[body];
+                    format!("{:.1},{:.1}", x(step), y(value))
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            svg += &format!(
+                "<polyline fill=\"none\" stroke=\"{}\" points=\"{}\"/>\n",
+                COLORS[body % COLORS.len()],
+                points
+            );
+        }
+        svg + "</svg>\n"
     }
 
-    fn is_static(&self) -> bool {
-        self.speed.into_iter().all(|s| s == 0)
+    pub fn write_ply(
+        &self,
+        range: Range<usize>,
+        every: usize,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        let steps = self.sample(&range, every);
+        writeln!(writer, "ply\nformat ascii 1.0")?;
+        writeln!(writer, "element vertex {}", steps.len() * self.bodies())?;
+        for name in ["x", "y", "z"] {
+            writeln!(writer, "property int {}", name)?;
+        }
+        writeln!(writer, "property int body\nproperty int step\nend_header")?;
+        for &step in &steps {
+            for body in 0..self.bodies() {
+                let mut position = self.position(step, body);
+                position.resize(3, 0);
+                writeln!(
+                    writer,
+                    "{} {} {} {} {}",
+                    position[0], position[1], position[2], body, step
+                )?;
+            }
+        }
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+}
+
+#[derive(Clone)]
+struct Hashed<T> {
+    hash: u64,
+    state: T,
+}
+
+impl<T: Hash + Eq> Hashed<T> {
+    fn new(state: T) -> Self {
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        Self {
+            hash: hasher.finish(),
+            state,
+        }
+    }
+
+    fn same(&self, other: &Self) -> bool {
+        self.hash == other.hash && self.state == other.state
+    }
+}
+
+pub fn find_cycle<T: Clone + Hash + Eq>(start: &T, step: impl Fn(&T) -> T) -> Cycle {
+    let next = |x: &Hashed<T>| Hashed::new(step(&x.state));
+    let first = Hashed::new(start.clone());
+
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = first.clone();
+    let mut hare = next(&first);
+    while !tortoise.same(&hare) {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        hare = next(&hare);
+        length += 1;
+    }
+
+    let mut tortoise = first.clone();
+    let mut hare = first;
+    for _ in 0..length {
+        hare = next(&hare);
+    }
+    let mut start = 0;
+    while !tortoise.same(&hare) {
+        tortoise = next(&tortoise);
+        hare = next(&hare);
+        start += 1;
+    }
+    Cycle { start, length }
+}
+
 fn gcd(mut a: i64, mut b: i64) -> i64 {
     if a < b {
         std::mem::swap(&mut a, &mut b);
@@ -42,81 +533,15 @@ fn gcd(mut a: i64, mut b: i64) -> i64 {
     }
 }
 
-fn apply_gravity(first: &mut Moon, second: &mut Moon) {
-    for i in 0..3 {
-        if first.pos[i] > second.pos[i] {
-            first.speed[i] -= 1;
-            second.speed[i] += 1;
-        } else if first.pos[i] < second.pos[i] {
-            first.speed[i] += 1;
-            second.speed[i] -= 1;
-        }
-    }
-}
-
-fn simulate(moons: &mut [Moon], steps: usize) -> i64 {
-    (0..steps).for_each(|_| {
-        (0..moons.len())
-            .tuple_combinations()
-            .for_each(|pair: (usize, usize)| {
-                let (first, second) = moons.split_at_mut(pair.1);
-                apply_gravity(&mut first[pair.0], &mut second[0]);
-            });
-        moons.iter_mut().for_each(|moon| moon.update());
-    });
-    moons.iter().map(|moon| moon.energy()).sum()
-}
-
-fn parse(input: &str) -> Vec<Moon> {
-    let re = Regex::new(r"<x=(-?\d+), y=(-?\d+), z=(-?\d+)>").unwrap();
-    re.captures_iter(input)
-        .map(|captures| {
-            let values: [i64; 3] = captures
-                .extract::<3>()
-                .1
-                .iter()
-                .map(|x| x.parse().unwrap())
-                .collect::<Vec<_>>()
-                .try_into()
-                .unwrap();
-            Moon {
-                pos: values,
-                speed: [0; 3],
-            }
-        })
-        .collect()
-}
-
 pub fn part1(input: &str) -> i64 {
-    let mut moons = parse(input);
-    simulate(&mut moons, 1000)
+    let mut system = System::parse(input);
+    system.run(1000);
+    system.energy()
 }
 
 pub fn part2(input: &str) -> i64 {
-    let mut moons = parse(input);
-    let initials = moons.iter().map(|moon| moon.pos).collect::<Vec<_>>();
-    let mut cycles: Vec<Option<i64>> = vec![None; 3];
-    let mut cycles_left = cycles.len();
-    for i in 1.. {
-        simulate(&mut moons, 1);
-        for k in 0..3 {
-            if cycles[k].is_none()
-                && moons
-                    .iter()
-                    .zip(initials.iter())
-                    .all(|(moon, initial)| moon.speed[k] == 0 && moon.pos[k] == initial[k])
-            {
-                cycles[k] = Some(i);
-                cycles_left -= 1;
-                if cycles_left == 0 {
-                    let lcm = cycles[0].unwrap() * cycles[1].unwrap()
-                        / gcd(cycles[0].unwrap(), cycles[1].unwrap());
-                    return cycles[2].unwrap() * lcm / gcd(lcm, cycles[2].unwrap());
-                }
-            }
-        }
-    }
-    -1
+    let (_, cycle) = System::parse(input).cycles();
+    (cycle.start + cycle.length) as i64
 }
 
 #[cfg(test)]
@@ -139,11 +564,15 @@ mod tests {
 
     #[test]
     fn test_day12_part1() {
-        let mut moons = parse(INPUT1);
-        assert_eq!(simulate(&mut moons, 10), 179);
+        let mut system = System::parse(INPUT1);
+        system.run(10);
+        assert_eq!(system.energy(), 179);
+        assert_eq!(system.position(0), vec![2, 1, -3]);
+        assert_eq!(system.velocity(0), vec![-3, -2, 1]);
 
-        moons = parse(INPUT2);
-        assert_eq!(simulate(&mut moons, 100), 1940);
+        system = System::parse(INPUT2);
+        system.run(100);
+        assert_eq!(system.energy(), 1940);
     }
 
     #[test]
@@ -151,4 +580,172 @@ mod tests {
         assert_eq!(part2(INPUT1), 2772);
         assert_eq!(part2(INPUT2), 4686774924);
     }
+
+    #[test]
+    fn test_day12_system() {
+        let system = System::parse("<a=0, b=5> <a=3, b=5>\n<a=3, b=-1>\n<a=10, b=2>");
+        assert_eq!(system.axis_names(), ["a", "b"]);
+        assert_eq!((system.bodies(), system.dimensions()), (4, 2));
+        let next = system.states().nth(1).unwrap();
+        assert_eq!(next.velocity(0), vec![3, -2]);
+        assert_eq!(next.velocity(1), vec![0, -2]);
+        assert_eq!(next.velocity(2), vec![0, 3]);
+        assert_eq!(next.velocity(3), vec![-3, 1]);
+        assert_eq!(next.position(3), vec![7, 3]);
+        for state in system.states().take(50) {
+            assert_eq!(state.momentum(), vec![0, 0]);
+        }
+
+        let line = System::parse("<x=1> <x=4> <x=9>");
+        assert_eq!(line.states().nth(2).unwrap().position(1), vec![4]);
+
+        fn send_sync<T: Send + Sync>(_: &T) {}
+        send_sync(&system);
+        let energy = std::thread::scope(|scope| {
+            scope
+                .spawn(|| system.states().nth(10).unwrap().energy())
+                .join()
+                .unwrap()
+        });
+        assert_eq!(energy, system.states().nth(10).unwrap().energy());
+    }
+
+    #[test]
+    fn test_day12_cycles() {
+        let (axes, system) = System::parse(INPUT1).cycles();
+        let lengths = axes.iter().map(|cycle| cycle.length).collect::<Vec<_>>();
+        assert_eq!(lengths, vec![18, 28, 44]);
+        assert!(axes.iter().all(|cycle| cycle.start == 0));
+        assert_eq!(
+            system,
+            Cycle {
+                start: 0,
+                length: 2772
+            }
+        );
+
+        let step = |x: &u64| (x * x + 1) % 1009;
+        let mut seen = std::collections::HashMap::new();
+        let mut x = 3;
+        for i in 0.. {
+            if let Some(first) = seen.insert(x, i) {
+                assert_eq!(
+                    find_cycle(&3, step),
+                    Cycle {
+                        start: first,
+                        length: i - first
+                    }
+                );
+                break;
+            }
+            x = step(&x);
+        }
+        assert_eq!(
+            find_cycle(&7, |_| 7),
+            Cycle {
+                start: 0,
+                length: 1
+            }
+        );
+        assert_eq!(
+            find_cycle(&0, |&x: &u32| (x + 1).min(5)),
+            Cycle {
+                start: 5,
+                length: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_day12_force_laws() {
+        let system = System::parse("<x=0> <x=2> <x=10>").with_masses(&[1, 3, 2]);
+        let next = system.states().nth(1).unwrap();
+        assert_eq!(
+            [next.velocity(0), next.velocity(1), next.velocity(2)],
+            [vec![5], vec![1], vec![-4]]
+        );
+        for state in system.states().take(50) {
+            assert_eq!(state.momentum(), vec![0]);
+        }
+        let system = System::parse("<x=0, y=3> <x=7, y=-2> <x=3, y=5> <x=-4, y=1>")
+            .with_masses(&[1, 3, 2, 5])
+            .with_law(InverseDistance { strength: 12 });
+        for state in system.states().take(50) {
+            assert_eq!(state.momentum(), vec![0, 0]);
+        }
+
+        let axis = Axis::new(vec![0, 4, 2, 4]);
+        assert_eq!(
+            InverseDistance { strength: 8 }.accelerations(&axis, &[1, 1, 2, 1]),
+            vec![12, -10, 4, -10]
+        );
+        let axis = Axis {
+            positions: vec![0, 5],
+            velocities: vec![9, -4],
+        };
+        let damped = Damped::new(UnitGravity, 3);
+        assert_eq!(damped.accelerations(&axis, &[1, 1]), vec![-2, 0]);
+
+        let system = System::parse(
+            "<x=-1, y=0, z=2>\n<x=2, y=-10, z=-7>\n<x=4, y=-8, z=8>\n<x=3, y=5, z=-1>",
+        )
+        .with_law(Damped::new(UnitGravity, 1));
+        let (axes, _) = system.cycles();
+        for (k, cycle) in axes.iter().enumerate() {
+            let trajectory = system.record(cycle.start + cycle.length);
+            assert_eq!(
+                trajectory.frames[cycle.start][k],
+                trajectory.frames[cycle.start + cycle.length][k]
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "damping divisor must not be zero")]
+    fn test_day12_damped_zero_divisor() {
+        Damped::new(UnitGravity, 0);
+    }
+
+    #[test]
+    fn test_day12_trajectory_csv() {
+        let trajectory = System::parse("<x=0, y=1>\n<x=2, y=1>").record(2);
+        assert_eq!(trajectory.steps(), 3);
+        let mut csv = vec![];
+        trajectory.write_csv(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "step,body,x,y,vx,vy
+0,0,0,1,0,0
+0,1,2,1,0,0
+1,0,1,1,1,0
+1,1,1,1,-1,0
+2,0,2,1,1,0
+2,1,0,1,-1,0
+"
+        );
+    }
+
+    #[test]
+    fn test_day12_plots() {
+        let system = System::parse(INPUT1);
+        let (axes, _) = system.cycles();
+        let trajectory = system.record(40);
+        let svg = trajectory.to_svg(0, 0..100, 2, Some(axes[0]));
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">x (steps 0..=40)</text>"));
+        assert!(svg.contains(">cycle start 0, length 18</text>"));
+        assert_eq!(svg.matches("stroke-dasharray").count(), 3);
+        assert_eq!(svg.matches("<polyline").count(), 4);
+        let points = svg.split("points=\"").nth(1).unwrap();
+        assert_eq!(points.split('"').next().unwrap().split(' ').count(), 21);
+
+        let mut ply = vec![];
+        System::parse("<x=0, y=1>\n<x=2, y=1>")
+            .record(3)
+            .write_ply(1..3, 1, &mut ply)
+            .unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        assert!(ply.starts_with("ply\nformat ascii 1.0\nelement vertex 4\n"));
+        assert!(ply.ends_with("end_header\n1 1 0 0 1\n1 1 0 1 1\n2 1 0 0 2\n0 1 0 1 2\n"));
+    }
 }
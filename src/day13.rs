@@ -1,12 +1,19 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
-#[derive(Debug)]
+use itertools::Itertools;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::recorder::FrameRecorder;
+
+#[derive(Debug, Clone)]
 enum IntCodeState {
     Created,
     WaitInput,
     Halted,
 }
 
+#[derive(Clone)]
 struct IntCode {
     memory: Vec<i64>,
     index: usize,
@@ -142,7 +149,7 @@ impl IntCode {
     }
 }
 
-fn parse(input: &str) -> Vec<i64> {
+pub fn parse(input: &str) -> Vec<i64> {
     input
         .trim()
         .split(",")
@@ -161,42 +168,245 @@ pub fn part1(input: &str) -> i64 {
         .count() as i64
 }
 
-pub fn part2(input: &str) -> i64 {
-    let mut values = parse(input);
-    values[0] = 2;
-    let mut int_code = IntCode::new(values, VecDeque::new());
-    int_code.execute();
-    let (max_x, max_y) = int_code.output.chunks(3).fold((0, 0), |acc, chunk| {
-        (acc.0.max(chunk[0]), acc.1.max(chunk[1]))
-    });
-    let mut field = vec![vec![b' '; max_x as usize + 1]; max_y as usize + 1];
-    let mut prev_ball = (0, 0);
-    let mut ball = (0, 0);
-    let mut pos = (0, 0);
-    let mut result = 0;
-    while !int_code.output.is_empty() {
-        let mut i = 0;
-        while i < int_code.output.len() {
-            let chunk = &int_code.output[i..i + 3];
-            match chunk {
-                [-1, 0, points] => result = *points,
-                [x, y, 0] => field[*y as usize][*x as usize] = b' ',
-                [x, y, 1] => field[*y as usize][*x as usize] = b'#',
-                [x, y, 2] => field[*y as usize][*x as usize] = b'*',
-                [x, y, 3] => {
-                    field[*y as usize][*x as usize] = b'-';
-                    pos = (*x, *y);
-                }
-                [x, y, 4] => {
-                    field[*y as usize][*x as usize] = b'o';
-                    prev_ball = ball;
-                    ball = (*x, *y);
+const UNKNOWN_TILE: u8 = 5;
+
+pub const PALETTE: [[u8; 3]; 5] = [
+    [0, 0, 0],
+    [128, 128, 128],
+    [230, 120, 40],
+    [240, 240, 240],
+    [220, 30, 30],
+];
+
+pub trait Controller {
+    fn joystick(&mut self, arcade: &Arcade) -> i64;
+}
+
+#[derive(Clone)]
+pub struct Arcade {
+    int_code: IntCode,
+    screen: Vec<Vec<u8>>,
+    score: i64,
+    ball: (i64, i64),
+    prev_ball: (i64, i64),
+    paddle: (i64, i64),
+    inputs: Vec<i64>,
+}
+
+impl Arcade {
+    pub fn new(program: Vec<i64>) -> Self {
+        let mut arcade = Self {
+            int_code: IntCode::new(program, VecDeque::new()),
+            screen: vec![],
+            score: 0,
+            ball: (0, 0),
+            prev_ball: (0, 0),
+            paddle: (0, 0),
+            inputs: vec![],
+        };
+        arcade.int_code.execute();
+        arcade.update();
+        arcade
+    }
+
+    fn update(&mut self) {
+        for chunk in self.int_code.output.chunks(3) {
+            match *chunk {
+                [-1, 0, points] => self.score = points,
+                [x, y, tile] => {
+                    let (col, row) = (x as usize, y as usize);
+                    let width = self.screen.first().map_or(0, |first| first.len());
+                    if width <= col {
+                        self.screen
+                            .iter_mut()
+                            .for_each(|line| line.resize(col + 1, 0));
+                    }
+                    if self.screen.len() <= row {
+                        self.screen.resize(row + 1, vec![0; width.max(col + 1)]);
+                    }
+                    self.screen[row][col] = match tile {
+                        0..=4 => tile as u8,
+                        _ => UNKNOWN_TILE,
+                    };
+                    match tile {
+                        3 => self.paddle = (x, y),
+                        4 => {
+                            self.prev_ball = self.ball;
+                            self.ball = (x, y);
+                        }
+                        _ => (),
+                    }
                 }
                 _ => panic!("Unexpected"),
             }
-            i += 3;
         }
-        int_code.output.clear();
+        self.int_code.output.clear();
+    }
+
+    pub fn step(&mut self, joystick: i64) {
+        self.inputs.push(joystick);
+        self.int_code.add_input(joystick);
+        self.int_code.execute();
+        self.update();
+    }
+
+    pub fn run(&mut self, controller: &mut dyn Controller) -> i64 {
+        while !self.is_halted() {
+            let joystick = controller.joystick(self);
+            self.step(joystick);
+        }
+        self.score
+    }
+
+    pub fn run_recorded(
+        &mut self,
+        controller: &mut dyn Controller,
+        recorder: &mut FrameRecorder,
+    ) -> i64 {
+        recorder.capture(&self.screen);
+        while !self.is_halted() {
+            let joystick = controller.joystick(self);
+            self.step(joystick);
+            recorder.capture(&self.screen);
+        }
+        self.score
+    }
+
+    pub fn is_halted(&self) -> bool {
+        matches!(self.int_code.state, IntCodeState::Halted)
+    }
+
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    pub fn ball(&self) -> (i64, i64) {
+        self.ball
+    }
+
+    pub fn prev_ball(&self) -> (i64, i64) {
+        self.prev_ball
+    }
+
+    pub fn paddle(&self) -> (i64, i64) {
+        self.paddle
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.screen
+            .iter()
+            .flatten()
+            .filter(|&&tile| tile == 2)
+            .count()
+    }
+
+    pub fn screen(&self) -> &[Vec<u8>] {
+        &self.screen
+    }
+
+    pub fn inputs(&self) -> &[i64] {
+        &self.inputs
+    }
+
+    pub fn render(&self) -> String {
+        self.screen
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&tile| b" #*-o?"[tile.min(UNKNOWN_TILE) as usize] as char)
+                    .collect()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn save(&self) -> String {
+        [
+            format!("memory={}", self.int_code.memory.iter().join(",")),
+            format!("index={}", self.int_code.index),
+            format!("relative_base={}", self.int_code.relative_base),
+            format!("state={:?}", self.int_code.state),
+            format!("input={}", self.int_code.input.iter().join(",")),
+            format!("score={}", self.score),
+            format!("ball={},{}", self.ball.0, self.ball.1),
+            format!("prev_ball={},{}", self.prev_ball.0, self.prev_ball.1),
+            format!("paddle={},{}", self.paddle.0, self.paddle.1),
+            format!("inputs={}", self.inputs.iter().join(",")),
+            format!(
+                "screen={}",
+                self.screen
+                    .iter()
+                    .map(|row| row.iter().map(|tile| tile.to_string()).join(""))
+                    .join(",")
+            ),
+        ]
+        .join("\n")
+    }
+
+    pub fn restore(data: &str) -> Option<Self> {
+        let fields = data
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .collect::<HashMap<_, _>>();
+        let values = |key: &str| -> Option<Vec<i64>> {
+            fields
+                .get(key)?
+                .split(',')
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse().ok())
+                .collect()
+        };
+        let pair = |key: &str| -> Option<(i64, i64)> {
+            match values(key)?.as_slice() {
+                &[x, y] => Some((x, y)),
+                _ => None,
+            }
+        };
+        let state = match *fields.get("state")? {
+            "Created" => IntCodeState::Created,
+            "WaitInput" => IntCodeState::WaitInput,
+            "Halted" => IntCodeState::Halted,
+            _ => return None,
+        };
+        let screen = fields
+            .get("screen")?
+            .split(',')
+            .filter(|row| !row.is_empty())
+            .map(|row| {
+                row.bytes()
+                    .map(|tile| matches!(tile, b'0'..=b'5').then_some(tile - b'0'))
+                    .collect()
+            })
+            .collect::<Option<Vec<Vec<u8>>>>()?;
+        Some(Self {
+            int_code: IntCode {
+                memory: values("memory")?,
+                index: fields.get("index")?.parse().ok()?,
+                input: values("input")?.into(),
+                output: vec![],
+                state,
+                relative_base: fields.get("relative_base")?.parse().ok()?,
+            },
+            screen,
+            score: fields.get("score")?.parse().ok()?,
+            ball: pair("ball")?,
+            prev_ball: pair("prev_ball")?,
+            paddle: pair("paddle")?,
+            inputs: values("inputs")?,
+        })
+    }
+}
+
+fn direction(from: i64, to: i64) -> i64 {
+    (to - from).signum()
+}
+
+#[derive(Default)]
+pub struct FollowBall;
+
+impl Controller for FollowBall {
+    fn joystick(&mut self, arcade: &Arcade) -> i64 {
+        let (ball, prev_ball, pos) = (arcade.ball(), arcade.prev_ball(), arcade.paddle());
         let target_x = if ball.1 + 1 == pos.1 {
             ball.0
         } else if ball.0 > prev_ball.0 {
@@ -204,24 +414,142 @@ pub fn part2(input: &str) -> i64 {
         } else {
             ball.0 - 1
         };
-        if target_x > pos.0 {
-            int_code.add_input(1);
-        } else if target_x < pos.0 {
-            int_code.add_input(-1);
-        } else {
-            int_code.add_input(0);
+        direction(pos.0, target_x)
+    }
+}
+
+#[derive(Default)]
+pub struct Predictor {
+    target: Option<i64>,
+}
+
+impl Controller for Predictor {
+    fn joystick(&mut self, arcade: &Arcade) -> i64 {
+        let (ball, pos) = (arcade.ball(), arcade.paddle());
+        if ball.1 + 1 == pos.1 {
+            self.target = None;
+            return direction(pos.0, ball.0);
         }
+        let target = *self.target.get_or_insert_with(|| {
+            let mut future = arcade.clone();
+            while !future.is_halted() {
+                future.step(0);
+                if future.ball().1 + 1 == future.paddle().1 {
+                    break;
+                }
+            }
+            future.ball().0
+        });
+        direction(pos.0, target)
+    }
+}
+
+pub struct RandomController {
+    rng: StdRng,
+}
+
+impl RandomController {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
 
-        int_code.execute();
+impl Controller for RandomController {
+    fn joystick(&mut self, _: &Arcade) -> i64 {
+        self.rng.gen_range(-1..=1)
     }
-    result
+}
+
+pub fn part2(input: &str) -> i64 {
+    let mut values = parse(input);
+    values[0] = 2;
+    Arcade::new(values).run(&mut FollowBall)
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn session(frames: &[&[i64]]) -> Vec<i64> {
+        let mut program = vec![];
+        for (i, frame) in frames.iter().enumerate() {
+            if i > 0 {
+                program.extend([3, 0]);
+            }
+            frame.iter().for_each(|&value| program.extend([104, value]));
+        }
+        program.push(99);
+        program
+    }
+
+    const FRAMES: [&[i64]; 4] = [
+        &[0, 0, 1, 3, 0, 2, 2, 4, 3, 1, 1, 4, -1, 0, 0],
+        &[1, 1, 0, 2, 2, 4],
+        &[2, 2, 0, 2, 4, 0, 3, 4, 3, 3, 3, 4],
+        &[3, 3, 0, 2, 2, 4, 3, 0, 0, -1, 0, 1],
+    ];
+
     #[test]
     fn test_day13_part1() {}
 
     #[test]
     fn test_day13_part2() {}
+
+    #[test]
+    fn test_day13_arcade() {
+        let arcade = Arcade::new(session(&FRAMES));
+        assert_eq!(arcade.ball(), (1, 1));
+        assert_eq!(arcade.paddle(), (2, 4));
+        assert_eq!(arcade.blocks(), 1);
+        assert_eq!(arcade.screen()[0], vec![1, 0, 0, 2]);
+        assert_eq!(arcade.render(), "#  *\n o  \n    \n    \n  - ");
+        let mut unknown = Arcade::new(vec![104, 1, 104, 0, 104, 7, 99]);
+        unknown.step(0);
+        assert_eq!(unknown.render(), " ?");
+        assert_eq!(unknown.screen()[0], vec![0, UNKNOWN_TILE]);
+        let restored = Arcade::restore(&unknown.save()).unwrap();
+        assert_eq!(restored.render(), " ?");
+    }
+
+    #[test]
+    fn test_day13_recorder() {
+        let mut arcade = Arcade::new(session(&FRAMES));
+        let mut recorder = FrameRecorder::new(&PALETTE, 4);
+        assert_eq!(arcade.run_recorded(&mut FollowBall, &mut recorder), 1);
+        assert_eq!(recorder.frames().len(), 4);
+        assert_eq!(recorder.frames()[0], Arcade::new(session(&FRAMES)).screen());
+        assert_eq!(recorder.frames()[3], arcade.screen());
+    }
+
+    #[test]
+    fn test_day13_save_restore() {
+        let mut arcade = Arcade::new(session(&FRAMES));
+        arcade.step(1);
+        let mut restored = Arcade::restore(&arcade.save()).unwrap();
+        assert_eq!(restored.save(), arcade.save());
+        assert_eq!(restored.run(&mut FollowBall), 1);
+        assert_eq!(restored.inputs(), [1, 1, 0]);
+        assert!(Arcade::restore("score=1").is_none());
+    }
+
+    #[test]
+    fn test_day13_controllers() {
+        let mut arcade = Arcade::new(session(&FRAMES));
+        assert_eq!(arcade.run(&mut FollowBall), 1);
+        assert_eq!(arcade.inputs(), [0, 1, 0]);
+        assert_eq!(arcade.blocks(), 0);
+
+        let mut arcade = Arcade::new(session(&FRAMES));
+        assert_eq!(arcade.run(&mut Predictor::default()), 1);
+        assert_eq!(arcade.inputs(), [1, 1, 0]);
+
+        let mut first = Arcade::new(session(&FRAMES));
+        first.run(&mut RandomController::new(42));
+        let mut second = Arcade::new(session(&FRAMES));
+        second.run(&mut RandomController::new(42));
+        assert_eq!(first.inputs(), second.inputs());
+        assert!(first.inputs().iter().all(|input| (-1..=1).contains(input)));
+    }
 }
@@ -1,4 +1,7 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+};
 
 #[derive(Debug)]
 struct Chemical {
@@ -7,8 +10,10 @@ struct Chemical {
 }
 
 impl Chemical {
-    fn is_primitive(&self) -> bool {
-        return self.ingredients.len() == 1 && self.ingredients[0].0 == "ORE";
+    fn is_primitive(&self, data: &HashMap<String, Chemical>) -> bool {
+        self.ingredients
+            .iter()
+            .all(|(name, _)| !data.contains_key(name))
     }
 
     fn disassemble(&self, data: &HashMap<String, Chemical>) -> HashMap<String, i64> {
@@ -16,7 +21,7 @@ impl Chemical {
             .iter()
             .fold(HashMap::new(), |mut acc, (name, amount)| {
                 let chemical = data.get(name).unwrap();
-                if !chemical.is_primitive() {
+                if !chemical.is_primitive(data) {
                     chemical
                         .disassemble(data)
                         .into_iter()
@@ -43,34 +48,260 @@ impl Chemical {
     }
 }
 
-fn parse_chemical(input: &str) -> (String, i64) {
-    let parts = input.split(" ").collect::<Vec<_>>();
-    (parts[1].to_string(), parts[0].parse().unwrap())
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    Malformed {
+        line: usize,
+    },
+    UnknownIngredient {
+        line: usize,
+        name: String,
+    },
+    DuplicateRecipe {
+        line: usize,
+        first: usize,
+        name: String,
+    },
+    Cycle {
+        lines: Vec<usize>,
+        chemicals: Vec<String>,
+    },
+    MissingTarget(String),
+    Unreachable {
+        line: usize,
+        name: String,
+    },
 }
 
-fn parse(input: &str) -> HashMap<String, Chemical> {
-    input.lines().fold(
-        HashMap::new(),
-        |mut acc: HashMap<String, Chemical>, line| {
-            let parts = line.trim().split(" => ").collect::<Vec<_>>();
-            let (chemical, amount) = parse_chemical(parts[1]);
-            acc.insert(
-                chemical,
-                Chemical {
-                    amount,
-                    ingredients: parts[0].split(", ").map(parse_chemical).collect(),
-                },
-            );
-            acc
-        },
-    )
+impl Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Finding::Malformed { line } => write!(f, "line {}: malformed reaction", line),
+            Finding::UnknownIngredient { line, name } => {
+                write!(f, "line {}: unknown ingredient {}", line, name)
+            }
+            Finding::DuplicateRecipe { line, first, name } => write!(
+                f,
+                "line {}: second recipe for {} (first on line {})",
+                line, name, first
+            ),
+            Finding::Cycle { lines, chemicals } => write!(
+                f,
+                "lines {}: cycle {}",
+                lines
+                    .iter()
+                    .map(|line| line.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                chemicals.join(" -> ")
+            ),
+            Finding::MissingTarget(name) => write!(f, "no recipe produces {}", name),
+            Finding::Unreachable { line, name } => {
+                write!(f, "line {}: recipe for {} is never used", line, name)
+            }
+        }
+    }
+}
+
+type Term<'a> = (&'a str, i64);
+
+fn parse_term(input: &str) -> Option<Term<'_>> {
+    let (amount, name) = input.trim().split_once(' ')?;
+    let amount = amount.parse().ok().filter(|&amount| amount >= 0)?;
+    let valid = !name.is_empty() && name.chars().all(|ch| ch.is_ascii_alphanumeric());
+    valid.then_some((name, amount))
+}
+
+struct Recipe<'a> {
+    line: usize,
+    output: &'a str,
+    amount: i64,
+    ingredients: Vec<Term<'a>>,
+}
+
+fn parse_recipe(line: &str) -> Option<(Term<'_>, Vec<Term<'_>>)> {
+    let (inputs, output) = line.trim().split_once(" => ")?;
+    let output = parse_term(output)?;
+    let ingredients = inputs
+        .split(", ")
+        .map(parse_term)
+        .collect::<Option<Vec<_>>>()?;
+    (output.1 > 0).then_some((output, ingredients))
+}
+
+pub fn validate(input: &str, raw: Option<&[&str]>, target: Option<&str>) -> Vec<Finding> {
+    check(input, raw, target).1
+}
+
+fn check<'a>(
+    input: &'a str,
+    raw: Option<&[&str]>,
+    target: Option<&str>,
+) -> (Vec<Recipe<'a>>, Vec<Finding>) {
+    let mut findings = vec![];
+    let mut recipes: Vec<Recipe> = vec![];
+    let mut index: HashMap<&str, usize> = HashMap::new();
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some(((output, amount), ingredients)) = parse_recipe(line) else {
+            findings.push(Finding::Malformed { line: i + 1 });
+            continue;
+        };
+        if let Some(&first) = index.get(output) {
+            findings.push(Finding::DuplicateRecipe {
+                line: i + 1,
+                first: recipes[first].line,
+                name: output.to_string(),
+            });
+            continue;
+        }
+        index.insert(output, recipes.len());
+        recipes.push(Recipe {
+            line: i + 1,
+            output,
+            amount,
+            ingredients,
+        });
+    }
+
+    if let Some(raw) = raw {
+        for recipe in &recipes {
+            for (name, _) in &recipe.ingredients {
+                if !index.contains_key(name) && !raw.contains(name) {
+                    findings.push(Finding::UnknownIngredient {
+                        line: recipe.line,
+                        name: name.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    let edges = recipes
+        .iter()
+        .map(|recipe| {
+            recipe
+                .ingredients
+                .iter()
+                .filter_map(|(name, _)| index.get(name).copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut color = vec![0; recipes.len()];
+    for start in 0..recipes.len() {
+        if color[start] != 0 {
+            continue;
+        }
+        let mut stack = vec![(start, 0)];
+        color[start] = 1;
+        while let Some((node, next)) = stack.last_mut() {
+            let node = *node;
+            if let Some(&child) = edges[node].get(*next) {
+                *next += 1;
+                if color[child] == 0 {
+                    color[child] = 1;
+                    stack.push((child, 0));
+                } else if color[child] == 1 {
+                    let from = stack.iter().position(|&(n, _)| n == child).unwrap();
+                    let cycle = &stack[from..];
+                    findings.push(Finding::Cycle {
+                        lines: cycle.iter().map(|&(n, _)| recipes[n].line).collect(),
+                        chemicals: cycle
+                            .iter()
+                            .map(|&(n, _)| recipes[n].output.to_string())
+                            .chain([recipes[child].output.to_string()])
+                            .collect(),
+                    });
+                }
+            } else {
+                color[node] = 2;
+                stack.pop();
+            }
+        }
+    }
+
+    if let Some(target) = target {
+        match index.get(target) {
+            None => findings.push(Finding::MissingTarget(target.to_string())),
+            Some(&start) => {
+                let mut seen = HashSet::from([start]);
+                let mut stack = vec![start];
+                while let Some(node) = stack.pop() {
+                    for &child in &edges[node] {
+                        if seen.insert(child) {
+                            stack.push(child);
+                        }
+                    }
+                }
+                for (i, recipe) in recipes.iter().enumerate() {
+                    if !seen.contains(&i) {
+                        findings.push(Finding::Unreachable {
+                            line: recipe.line,
+                            name: recipe.output.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+    }
+    (recipes, findings)
+}
+
+impl Finding {
+    pub fn is_error(&self) -> bool {
+        !matches!(self, Finding::Unreachable { .. })
+    }
 }
 
-fn sort(chemicals: &HashMap<String, Chemical>) -> Vec<String> {
+fn checked_parse(
+    input: &str,
+    raw: Option<&[&str]>,
+    target: Option<&str>,
+) -> Result<HashMap<String, Chemical>, Vec<Finding>> {
+    let (recipes, findings) = check(input, raw, target);
+    let errors = findings
+        .into_iter()
+        .filter(Finding::is_error)
+        .collect::<Vec<_>>();
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(recipes
+        .into_iter()
+        .map(|recipe| {
+            let chemical = Chemical {
+                amount: recipe.amount,
+                ingredients: recipe
+                    .ingredients
+                    .into_iter()
+                    .map(|(name, amount)| (name.to_string(), amount))
+                    .collect(),
+            };
+            (recipe.output.to_string(), chemical)
+        })
+        .collect())
+}
+
+fn puzzle_parse(input: &str) -> HashMap<String, Chemical> {
+    checked_parse(input, Some(&["ORE"]), Some("FUEL")).unwrap_or_else(|errors| {
+        panic!(
+            "invalid reactions:\n{}",
+            errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    })
+}
+
+fn sort(chemicals: &HashMap<String, Chemical>, target: &str) -> Vec<String> {
     let mut result = vec![];
     let mut vertices: HashMap<String, i64> = HashMap::new();
-    vertices.insert("FUEL".to_string(), 0);
-    let mut stack = vec!["FUEL".to_string()];
+    vertices.insert(target.to_string(), 0);
+    let mut stack = vec![target.to_string()];
     while let Some(v) = stack.last() {
         let color = vertices.entry(v.to_string()).or_insert(0);
         if *color == 0 {
@@ -91,51 +322,386 @@ fn sort(chemicals: &HashMap<String, Chemical>) -> Vec<String> {
     result
 }
 
-fn solve(chemicals: &HashMap<String, Chemical>, amount: i64) -> i64 {
-    let mut sorted = sort(&chemicals);
-    let mut lefts: HashMap<String, i64> = HashMap::new();
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub chemical: String,
+    pub needed: i64,
+    pub runs: i64,
+    pub produced: i64,
+    pub surplus: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductionPlan {
+    pub target: String,
+    pub amount: i64,
+    pub steps: Vec<Step>,
+    pub raw: Vec<(String, i64)>,
+}
+
+impl ProductionPlan {
+    pub fn raw_amount(&self, name: &str) -> i64 {
+        self.raw
+            .iter()
+            .find(|(raw, _)| raw == name)
+            .map_or(0, |(_, amount)| *amount)
+    }
+
+    pub fn to_json(&self) -> String {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| {
+                format!(
+                    "{{\"chemical\":{},\"needed\":{},\"runs\":{},\"produced\":{},\"surplus\":{}}}",
+                    json_string(&step.chemical),
+                    step.needed,
+                    step.runs,
+                    step.produced,
+                    step.surplus
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        let raw = self
+            .raw
+            .iter()
+            .map(|(name, amount)| format!("{}:{}", json_string(name), amount))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"target\":{},\"amount\":{},\"steps\":[{}],\"raw\":{{{}}}}}",
+            json_string(&self.target),
+            self.amount,
+            steps,
+            raw
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Display for ProductionPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut rows = vec![[
+            "chemical".to_string(),
+            "needed".to_string(),
+            "runs".to_string(),
+            "produced".to_string(),
+            "surplus".to_string(),
+        ]];
+        for step in &self.steps {
+            rows.push([
+                step.chemical.clone(),
+                step.needed.to_string(),
+                step.runs.to_string(),
+                step.produced.to_string(),
+                step.surplus.to_string(),
+            ]);
+        }
+        for (name, amount) in &self.raw {
+            rows.push([
+                name.clone(),
+                amount.to_string(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]);
+        }
+        let widths = (0..5)
+            .map(|i| rows.iter().map(|row| row[i].len()).max().unwrap())
+            .collect::<Vec<_>>();
+        for row in rows {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(i, (cell, &width))| {
+                    if i == 0 {
+                        format!("{:<width$}", cell)
+                    } else {
+                        format!("{:>width$}", cell)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// A non-positive amount is clamped to zero: nothing has to be produced, so
+// every step and raw material in the plan comes out as zero.
+fn plan(chemicals: &HashMap<String, Chemical>, target: &str, amount: i64) -> ProductionPlan {
+    let amount = amount.max(0);
+    let mut sorted = sort(chemicals, target);
     let mut needed: HashMap<String, i64> = HashMap::new();
-    needed.insert("FUEL".to_string(), amount);
+    needed.insert(target.to_string(), amount);
+    let mut steps = vec![];
+    let mut raw = vec![];
     while let Some(name) = sorted.pop() {
+        let need = needed.get(&name).copied().unwrap_or(0);
         if let Some(chemical) = chemicals.get(&name) {
-            let (used, left) =
-                chemical.count(needed.get(&name).unwrap() - lefts.get(&name).unwrap_or(&0));
+            let (used, left) = chemical.count(need);
             for (used_ingr, used_amount) in used {
                 *needed.entry(used_ingr).or_default() += used_amount;
             }
-            lefts.insert(name, left);
+            steps.push(Step {
+                chemical: name,
+                needed: need,
+                runs: (need + left) / chemical.amount,
+                produced: need + left,
+                surplus: left,
+            });
         } else {
-            break;
+            raw.push((name, need));
         }
     }
-    *needed.get("ORE").unwrap()
+    ProductionPlan {
+        target: target.to_string(),
+        amount,
+        steps,
+        raw,
+    }
 }
 
-pub fn part1(input: &str) -> i64 {
-    let chemicals = parse(input);
-    solve(&chemicals, 1)
+pub struct Planner {
+    chemicals: HashMap<String, Chemical>,
 }
 
-pub fn part2(input: &str) -> i64 {
-    let chemicals = parse(input);
-    let mut low = 1;
-    let mut high = 1000000000000;
-    while low < high - 1 {
-        let mid = (low + high) / 2;
-        if solve(&chemicals, mid) > 1000000000000 {
-            high = mid;
-        } else {
-            low = mid;
+impl Planner {
+    pub fn parse(input: &str) -> Result<Self, Vec<Finding>> {
+        Ok(Self {
+            chemicals: checked_parse(input, None, None)?,
+        })
+    }
+
+    pub fn plan(&self, target: &str, amount: i64) -> ProductionPlan {
+        plan(&self.chemicals, target, amount)
+    }
+}
+
+pub fn production_plan(
+    input: &str,
+    target: &str,
+    amount: i64,
+) -> Result<ProductionPlan, Vec<Finding>> {
+    Ok(Planner::parse(input)?.plan(target, amount))
+}
+
+pub struct Reactions {
+    names: Vec<String>,
+    index: HashMap<String, usize>,
+    produced: Vec<i64>,
+    ingredients: Vec<Vec<(usize, i64)>>,
+    order: Vec<usize>,
+}
+
+impl Reactions {
+    fn new(chemicals: &HashMap<String, Chemical>) -> Self {
+        let mut names = chemicals.keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        let mut raw = chemicals
+            .values()
+            .flat_map(|chemical| chemical.ingredients.iter().map(|(name, _)| name))
+            .filter(|name| !chemicals.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        raw.sort();
+        raw.dedup();
+        names.extend(raw);
+        let index = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect::<HashMap<_, _>>();
+        let (produced, ingredients) = names
+            .iter()
+            .map(|name| match chemicals.get(name) {
+                Some(chemical) => (
+                    chemical.amount,
+                    chemical
+                        .ingredients
+                        .iter()
+                        .map(|(name, amount)| (index[name], *amount))
+                        .collect(),
+                ),
+                None => (0, vec![]),
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        let mut order = vec![];
+        let mut visited = vec![false; names.len()];
+        for start in 0..names.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut stack = vec![(start, 0)];
+            while let Some((node, next)) = stack.last_mut() {
+                let node = *node;
+                if let Some(&(child, _)) = ingredients[node].get(*next) {
+                    *next += 1;
+                    if !visited[child] {
+                        visited[child] = true;
+                        stack.push((child, 0));
+                    }
+                } else {
+                    order.push(node);
+                    stack.pop();
+                }
+            }
+        }
+        order.reverse();
+
+        Reactions {
+            names,
+            index,
+            produced,
+            ingredients,
+            order,
         }
     }
 
-    if solve(&chemicals, high) <= 1000000000000 {
-        high
-    } else {
-        low
+    pub fn parse(input: &str) -> Result<Self, Vec<Finding>> {
+        Ok(Self::new(&checked_parse(input, None, None)?))
+    }
+
+    fn fill(&self, target: usize, amount: i64, needed: &mut [i64]) -> Option<()> {
+        needed.fill(0);
+        needed[target] = amount;
+        for &node in &self.order {
+            let need = needed[node];
+            if need <= 0 || self.produced[node] == 0 {
+                continue;
+            }
+            let runs = (need - 1) / self.produced[node] + 1;
+            for &(ingredient, amount) in &self.ingredients[node] {
+                needed[ingredient] = needed[ingredient].checked_add(amount.checked_mul(runs)?)?;
+            }
+        }
+        Some(())
+    }
+
+    pub fn raw_needed(&self, target: &str, amount: i64) -> Vec<(String, i64)> {
+        let Some(&target) = self.index.get(target) else {
+            return vec![(target.to_string(), amount)];
+        };
+        let mut needed = vec![0; self.names.len()];
+        self.fill(target, amount, &mut needed)
+            .expect("raw material amounts overflow i64");
+        (0..self.names.len())
+            .filter(|&i| self.produced[i] == 0 && needed[i] > 0)
+            .map(|i| (self.names[i].clone(), needed[i]))
+            .collect()
+    }
+
+    pub fn max_product(&self, budget: &HashMap<&str, i64>, target: &str) -> Option<i64> {
+        let Some(&target) = self.index.get(target) else {
+            return Some(budget.get(target).copied().unwrap_or(0));
+        };
+        let available = self
+            .names
+            .iter()
+            .map(|name| budget.get(name.as_str()).copied().unwrap_or(0))
+            .collect::<Vec<_>>();
+        let mut needed = vec![0; self.names.len()];
+        self.fill(target, 1, &mut needed)?;
+        // Making `amount` units never needs more than `amount` times the raw
+        // materials of a single unit, so this is a lower bound that fits.
+        let mut low = (0..needed.len())
+            .filter(|&i| self.produced[i] == 0 && needed[i] > 0)
+            .map(|i| available[i] / needed[i])
+            .min()?;
+        // An overflowing fill needs more than i64::MAX of something: it never fits.
+        let mut fits = |amount: i64| {
+            self.fill(target, amount, &mut needed).is_some()
+                && (0..needed.len()).all(|i| self.produced[i] != 0 || needed[i] <= available[i])
+        };
+
+        let mut high = low.max(1);
+        while fits(high) {
+            if high == i64::MAX {
+                return None;
+            }
+            low = high;
+            high = high.saturating_mul(2);
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if fits(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some(low)
     }
 }
 
+fn solve(chemicals: &HashMap<String, Chemical>, amount: i64) -> i64 {
+    Reactions::new(chemicals)
+        .raw_needed("FUEL", amount)
+        .into_iter()
+        .find(|(name, _)| name == "ORE")
+        .map_or(0, |(_, amount)| amount)
+}
+
+pub fn part1(input: &str) -> i64 {
+    let chemicals = puzzle_parse(input);
+    solve(&chemicals, 1)
+}
+
+fn cost(
+    chemicals: &HashMap<String, Chemical>,
+    prices: &HashMap<&str, i64>,
+    target: &str,
+    amount: i64,
+) -> Option<i64> {
+    plan(chemicals, target, amount)
+        .raw
+        .iter()
+        .filter(|(_, amount)| *amount > 0)
+        .try_fold(0i64, |total, (name, amount)| {
+            prices
+                .get(name.as_str())?
+                .checked_mul(*amount)?
+                .checked_add(total)
+        })
+}
+
+pub fn cheapest_cost(
+    input: &str,
+    prices: &HashMap<&str, i64>,
+    target: &str,
+    amount: i64,
+) -> Result<Option<i64>, Vec<Finding>> {
+    Ok(cost(
+        &checked_parse(input, None, None)?,
+        prices,
+        target,
+        amount,
+    ))
+}
+
+pub fn max_product_for_budget(
+    input: &str,
+    budget: &HashMap<&str, i64>,
+    target: &str,
+) -> Result<Option<i64>, Vec<Finding>> {
+    Ok(Reactions::parse(input)?.max_product(budget, target))
+}
+
+pub fn part2(input: &str) -> i64 {
+    let chemicals = puzzle_parse(input);
+    Reactions::new(&chemicals)
+        .max_product(&HashMap::from([("ORE", 1000000000000)]), "FUEL")
+        .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -211,4 +777,241 @@ mod tests {
         assert_eq!(part2(INPUT4), 5586022);
         assert_eq!(part2(INPUT5), 460664);
     }
+
+    #[test]
+    fn test_day14_plan() {
+        let plan = production_plan(INPUT1, "FUEL", 1).unwrap();
+        assert_eq!(
+            plan.to_string(),
+            "chemical  needed  runs  produced  surplus
+FUEL           1     1         1        0
+E              1     1         1        0
+D              1     1         1        0
+C              1     1         1        0
+A             28     3        30        2
+B              1     1         1        0
+ORE           31
+"
+        );
+        assert_eq!(
+            plan.to_json(),
+            concat!(
+                r#"{"target":"FUEL","amount":1,"steps":["#,
+                r#"{"chemical":"FUEL","needed":1,"runs":1,"produced":1,"surplus":0},"#,
+                r#"{"chemical":"E","needed":1,"runs":1,"produced":1,"surplus":0},"#,
+                r#"{"chemical":"D","needed":1,"runs":1,"produced":1,"surplus":0},"#,
+                r#"{"chemical":"C","needed":1,"runs":1,"produced":1,"surplus":0},"#,
+                r#"{"chemical":"A","needed":28,"runs":3,"produced":30,"surplus":2},"#,
+                r#"{"chemical":"B","needed":1,"runs":1,"produced":1,"surplus":0}],"#,
+                r#""raw":{"ORE":31}}"#
+            )
+        );
+
+        let plan = production_plan(INPUT1, "C", 2).unwrap();
+        assert_eq!(plan.raw, vec![("ORE".to_string(), 22)]);
+        assert_eq!(
+            plan.steps[1],
+            Step {
+                chemical: "A".to_string(),
+                needed: 14,
+                runs: 2,
+                produced: 20,
+                surplus: 6
+            }
+        );
+
+        let plan = production_plan("1 ORE => 1 FUEL", "FUEL", -5).unwrap();
+        assert_eq!(plan.amount, 0);
+        assert_eq!(
+            plan.steps,
+            vec![Step {
+                chemical: "FUEL".to_string(),
+                needed: 0,
+                runs: 0,
+                produced: 0,
+                surplus: 0
+            }]
+        );
+        assert_eq!(plan.raw, vec![("ORE".to_string(), 0)]);
+    }
+
+    const INPUT6: &str = "10 ORE => 10 A
+        1 CLAY => 1 B
+        7 A, 1 B => 1 C
+        7 A, 1 C => 1 D
+        7 A, 1 D => 1 E
+        7 A, 1 E => 1 FUEL";
+
+    #[test]
+    fn test_day14_raw_materials() {
+        let prices = HashMap::from([("ORE", 2), ("CLAY", 5)]);
+        assert_eq!(cheapest_cost(INPUT6, &prices, "FUEL", 1), Ok(Some(65)));
+        assert_eq!(
+            cheapest_cost(INPUT6, &prices, "C", 3),
+            Ok(Some(2 * 30 + 5 * 3))
+        );
+        assert_eq!(
+            cheapest_cost(INPUT6, &HashMap::from([("ORE", 2)]), "FUEL", 1),
+            Ok(None)
+        );
+        let prices = HashMap::from([("ORE", i64::MAX)]);
+        assert_eq!(
+            cheapest_cost("1 ORE => 1 FUEL", &prices, "FUEL", 1),
+            Ok(Some(i64::MAX))
+        );
+        assert_eq!(
+            cheapest_cost("1 ORE => 1 FUEL", &prices, "FUEL", 2),
+            Ok(None)
+        );
+        let prices = HashMap::from([("ORE", i64::MAX), ("CLAY", 1)]);
+        assert_eq!(cheapest_cost(INPUT6, &prices, "B", 1), Ok(Some(1)));
+        assert_eq!(cheapest_cost(INPUT6, &prices, "FUEL", 1), Ok(None));
+
+        let budget = HashMap::from([("ORE", 100), ("CLAY", 3)]);
+        assert_eq!(max_product_for_budget(INPUT6, &budget, "FUEL"), Ok(Some(3)));
+        let budget = HashMap::from([("ORE", 120), ("CLAY", 10)]);
+        assert_eq!(max_product_for_budget(INPUT6, &budget, "FUEL"), Ok(Some(4)));
+        let budget = HashMap::from([("ORE", 120)]);
+        assert_eq!(max_product_for_budget(INPUT6, &budget, "FUEL"), Ok(Some(0)));
+        assert_eq!(
+            max_product_for_budget(INPUT6, &budget, "ORE"),
+            Ok(Some(120))
+        );
+        assert_eq!(
+            max_product_for_budget("0 ORE => 1 FREE", &budget, "FREE"),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_day14_reactions() {
+        for input in [INPUT2, INPUT3, INPUT4] {
+            let reactions = Reactions::parse(input).unwrap();
+            let chemicals = checked_parse(input, None, None).unwrap();
+            for ore in [0, 1000, 123456, 9876543] {
+                let fits = |amount| plan(&chemicals, "FUEL", amount).raw_amount("ORE") <= ore;
+                let fuel = reactions
+                    .max_product(&HashMap::from([("ORE", ore)]), "FUEL")
+                    .unwrap();
+                assert!(fits(fuel) && !fits(fuel + 1));
+            }
+        }
+
+        let reactions = Reactions::parse("1 ORE => 100000000 A\n1 A => 1 FUEL").unwrap();
+        let budget = HashMap::from([("ORE", 1)]);
+        assert_eq!(reactions.max_product(&budget, "FUEL"), Some(100000000));
+        let reactions = Reactions::parse("1 ORE => 1000000000 A\n1 A => 1 FUEL").unwrap();
+        let budget = HashMap::from([("ORE", 1000000000000)]);
+        assert_eq!(reactions.max_product(&budget, "FUEL"), None);
+        let budget = HashMap::from([("ORE", 1000000)]);
+        assert_eq!(
+            reactions.max_product(&budget, "FUEL"),
+            Some(1000000000000000)
+        );
+
+        assert_eq!(
+            Reactions::parse(INPUT6).unwrap().raw_needed("FUEL", 2),
+            vec![("CLAY".to_string(), 2), ("ORE".to_string(), 60)]
+        );
+    }
+
+    #[test]
+    fn test_day14_validate() {
+        let ore: &[&str] = &["ORE"];
+        assert_eq!(validate(INPUT5, Some(ore), Some("FUEL")), vec![]);
+        assert_eq!(
+            validate(INPUT6, Some(ore), Some("FUEL")),
+            vec![Finding::UnknownIngredient {
+                line: 2,
+                name: "CLAY".to_string()
+            }]
+        );
+        assert_eq!(validate(INPUT6, None, Some("FUEL")), vec![]);
+
+        let input = "10 ORE => 10 A
+            7 A, 1 C => 1 B
+
+            3 B => 1 C
+            2 ORE => 1 A
+            1 A => FUEL
+            4 A => 1 FUEL
+            1 ORE => 1 SPARE";
+        assert_eq!(
+            validate(input, Some(ore), Some("FUEL")),
+            vec![
+                Finding::DuplicateRecipe {
+                    line: 5,
+                    first: 1,
+                    name: "A".to_string()
+                },
+                Finding::Malformed { line: 6 },
+                Finding::Cycle {
+                    lines: vec![2, 4],
+                    chemicals: vec!["B".to_string(), "C".to_string(), "B".to_string()]
+                },
+                Finding::Unreachable {
+                    line: 2,
+                    name: "B".to_string()
+                },
+                Finding::Unreachable {
+                    line: 4,
+                    name: "C".to_string()
+                },
+                Finding::Unreachable {
+                    line: 8,
+                    name: "SPARE".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            validate("1 ORE => 1 A", Some(ore), Some("FUEL")),
+            vec![Finding::MissingTarget("FUEL".to_string())]
+        );
+        assert_eq!(
+            Finding::Cycle {
+                lines: vec![2, 4],
+                chemicals: vec!["B".to_string(), "C".to_string(), "B".to_string()]
+            }
+            .to_string(),
+            "lines 2, 4: cycle B -> C -> B"
+        );
+    }
+
+    #[test]
+    fn test_day14_irregular_whitespace() {
+        let input = "10 ORE => 10 A\n7 A,  1 ORE => 1 FUEL";
+        assert_eq!(validate(input, Some(&["ORE"]), Some("FUEL")), vec![]);
+        assert_eq!(part1(input), 11);
+        assert_eq!(
+            production_plan(input, "FUEL", 1).unwrap().raw,
+            vec![("ORE".to_string(), 11)]
+        );
+    }
+
+    #[test]
+    fn test_day14_unreachable_is_not_fatal() {
+        let input = "10 ORE => 10 A\n7 A => 1 FUEL\n1 ORE => 1 SPARE";
+        assert_eq!(
+            validate(input, Some(&["ORE"]), Some("FUEL")),
+            vec![Finding::Unreachable {
+                line: 3,
+                name: "SPARE".to_string()
+            }]
+        );
+        assert_eq!(part1(input), 10);
+        assert!(production_plan(input, "FUEL", 1).is_ok());
+
+        let broken = "10 ORE => 10 A\n7 A, 1 B => 1 FUEL\n1 FUEL => 1 B";
+        let errors = production_plan(broken, "FUEL", 1).unwrap_err();
+        assert!(errors.iter().all(Finding::is_error));
+        assert!(matches!(errors[..], [Finding::Cycle { .. }]));
+        assert!(cheapest_cost("1 ORE =>", &HashMap::new(), "FUEL", 1).is_err());
+        assert!(Reactions::parse("7 A => 1 FUEL\n8 A => 1 FUEL").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "line 1: unknown ingredient CLAY")]
+    fn test_day14_invalid_input() {
+        part1("1 CLAY => 1 FUEL");
+    }
 }
@@ -1,27 +1,274 @@
-fn process(input: &str, steps: usize) -> i64 {
-    let pattern = [0, 1, 0, -1];
-    let mut values = input
-        .trim()
-        .as_bytes()
-        .iter()
-        .map(|&ch| (ch as u8 - b'0') as i64)
-        .collect::<Vec<_>>();
-    for step in 0..steps {
-        let mut new_values = vec![0; values.len()];
-        for i in 0..values.len() {
-            for j in 0..values.len() {
-                new_values[i] += values[j] * pattern[((j + 1) / (i + 1)) % pattern.len()];
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Full,
+    PrefixSum,
+    SuffixSum,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transform {
+    pattern: Vec<i64>,
+    phases: usize,
+    offset: usize,
+    length: usize,
+    repeat: usize,
+    method: Option<Method>,
+    threads: usize,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new(&[0, 1, 0, -1])
+    }
+}
+
+impl Transform {
+    pub fn new(pattern: &[i64]) -> Self {
+        assert!(!pattern.is_empty(), "pattern must not be empty");
+        Self {
+            pattern: pattern.to_vec(),
+            phases: 100,
+            offset: 0,
+            length: 8,
+            repeat: 1,
+            method: None,
+            threads: 1,
+        }
+    }
+
+    pub fn with_phases(mut self, phases: usize) -> Self {
+        self.phases = phases;
+        self
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn with_length(mut self, length: usize) -> Self {
+        self.length = length;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: usize) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+
+    fn suffix_applies(&self, len: usize) -> bool {
+        self.pattern[0] == 0 && 2 * (self.offset + 1) > len
+    }
+
+    pub fn method(&self, len: usize) -> Method {
+        match self.method {
+            Some(method) => method,
+            None if self.suffix_applies(len) => Method::SuffixSum,
+            None => Method::PrefixSum,
+        }
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn run(&self, input: &str) -> Vec<u8> {
+        let digits = parse(input);
+        let len = digits.len() * self.repeat;
+        assert!(
+            self.offset + self.length <= len,
+            "window {}..{} is outside the signal of length {}",
+            self.offset,
+            self.offset + self.length,
+            len
+        );
+        assert!(
+            len <= u32::MAX as usize / 9,
+            "signal of length {} is too long",
+            len
+        );
+        let method = self.method(len);
+        assert!(
+            method != Method::SuffixSum || self.suffix_applies(len),
+            "suffix sums need a leading zero in the pattern and an offset in the second half"
+        );
+        let start = match method {
+            Method::Full => 0,
+            Method::PrefixSum if self.pattern[0] != 0 => 0,
+            Method::PrefixSum | Method::SuffixSum => self.offset,
+        };
+        let mut values = digits
+            .iter()
+            .cycle()
+            .take(len)
+            .skip(start)
+            .copied()
+            .collect::<Vec<_>>();
+        let edges = self.edges();
+        for _ in 0..self.phases {
+            values = match method {
+                Method::Full => self.map_digits(0, len, |i| self.full_digit(&values, i)),
+                Method::PrefixSum => {
+                    let prefix = prefix_sums(&values);
+                    self.map_digits(start, len, |i| self.prefix_digit(&edges, &prefix, start, i))
+                }
+                Method::SuffixSum => self.suffix_phase(&values),
+            };
+        }
+        values[self.offset - start..][..self.length].to_vec()
+    }
+
+    // The sum over a run is the difference of the prefix sums at its ends,
+    // so the prefix sum at a run boundary is weighted by the factor before
+    // it minus the factor after it.
+    fn edges(&self) -> Vec<i64> {
+        let runs = self.pattern.len();
+        (0..runs)
+            .map(|k| self.pattern[(k + runs - 1) % runs] - self.pattern[k])
+            .collect()
+    }
+
+    fn map_digits(&self, start: usize, len: usize, digit: impl Fn(usize) -> u8 + Sync) -> Vec<u8> {
+        let mut output = vec![0; len - start];
+        if self.threads == 1 {
+            for (i, value) in output.iter_mut().enumerate() {
+                *value = digit(start + i);
             }
-            new_values[i] = new_values[i].abs() % 10;
+            return output;
+        }
+
+        // Early digits have the shortest runs and cost the most, so the
+        // chunks are dealt out round-robin to keep the threads balanced.
+        let chunk = output.len().div_ceil(self.threads * 16).max(1);
+        let mut jobs = (0..self.threads).map(|_| vec![]).collect::<Vec<_>>();
+        for (k, slice) in output.chunks_mut(chunk).enumerate() {
+            jobs[k % self.threads].push((start + k * chunk, slice));
         }
-        values = new_values;
+        let digit = &digit;
+        thread::scope(|scope| {
+            for job in jobs {
+                scope.spawn(move || {
+                    for (base, slice) in job {
+                        for (i, value) in slice.iter_mut().enumerate() {
+                            *value = digit(base + i);
+                        }
+                    }
+                });
+            }
+        });
+        output
     }
-    let mut result = 0;
-    for i in 0..8 {
-        result *= 10;
-        result += values[i];
+
+    fn full_digit(&self, values: &[u8], i: usize) -> u8 {
+        let len = values.len();
+        let block = i + 1;
+        let mut sum = 0;
+        let mut run = 0;
+        let mut phase = run % self.pattern.len();
+        while run * block <= len {
+            let factor = self.pattern[phase];
+            if factor != 0 {
+                let from = (run * block).max(1) - 1;
+                let to = ((run + 1) * block - 1).min(len);
+                sum += factor * slice_sum(&values[from..to]);
+            }
+            run += 1;
+            phase += 1;
+            if phase == self.pattern.len() {
+                phase = 0;
+            }
+        }
+        (sum.abs() % 10) as u8
+    }
+
+    fn prefix_digit(&self, edges: &[i64], prefix: &[u32], start: usize, i: usize) -> u8 {
+        let len = start + prefix.len() - 1;
+        let block = i + 1;
+        let runs = self.pattern.len();
+        let period = runs * block;
+        let run_sum = |run: usize| {
+            let from = (run * block).max(start + 1) - 1 - start;
+            let to = ((run + 1) * block - 1).min(len) - start;
+            self.pattern[run % runs] * (prefix[to] - prefix[from]) as i64
+        };
+
+        // Inside whole pattern periods no clamping is needed: each run
+        // boundary is read once and weighted by the factor change across it.
+        let first = (start + 1).div_ceil(period);
+        let last = ((len + 1) / period).max(first);
+        let mut sum = ((start + 1) / block..first * runs)
+            .take_while(|run| run * block <= len)
+            .map(run_sum)
+            .sum::<i64>();
+        if first < last {
+            let from = first * period - 1 - start;
+            let to = last * period - 1 - start;
+            for chunk in prefix[from..to].chunks_exact(period) {
+                for (&edge, &value) in edges.iter().zip(chunk.iter().step_by(block)) {
+                    sum += edge * value as i64;
+                }
+            }
+            // The run just before the first period closes at `from` and the
+            // last run closes at `to`, both with the final factor.
+            sum += self.pattern[runs - 1] * (prefix[to] - prefix[from]) as i64;
+        }
+        sum += (last * runs..)
+            .take_while(|run| run * block <= len)
+            .map(run_sum)
+            .sum::<i64>();
+        (sum.abs() % 10) as u8
+    }
+
+    fn suffix_phase(&self, values: &[u8]) -> Vec<u8> {
+        let factor = (self.pattern[1 % self.pattern.len()].abs() % 10) as u32;
+        let mut sums = vec![0; values.len()];
+        let mut sum = 0;
+        for (total, &value) in sums.iter_mut().zip(values).rev() {
+            sum += value as u32;
+            *total = sum;
+        }
+        sums.iter()
+            .map(|&sum| (sum % 10 * factor % 10) as u8)
+            .collect()
+    }
+}
+
+fn slice_sum(values: &[u8]) -> i64 {
+    values
+        .chunks(1 << 24)
+        .map(|chunk| chunk.iter().map(|&value| value as u32).sum::<u32>() as i64)
+        .sum()
+}
+
+fn prefix_sums(values: &[u8]) -> Vec<u32> {
+    let mut prefix = Vec::with_capacity(values.len() + 1);
+    let mut sum = 0;
+    prefix.push(sum);
+    for &value in values {
+        sum += value as u32;
+        prefix.push(sum);
     }
-    result
+    prefix
+}
+
+fn parse(input: &str) -> Vec<u8> {
+    input.trim().bytes().map(|ch| ch - b'0').collect()
+}
+
+fn number(digits: &[u8]) -> i64 {
+    digits.iter().fold(0, |acc, &digit| acc * 10 + digit as i64)
+}
+
+fn process(input: &str, steps: usize) -> i64 {
+    number(&Transform::default().with_phases(steps).run(input))
 }
 
 pub fn part1(input: &str) -> i64 {
@@ -29,40 +276,19 @@ pub fn part1(input: &str) -> i64 {
 }
 
 pub fn part2(input: &str) -> i64 {
-    let pattern = [0, 1, 0, -1];
-    let mut offset = input
-        .trim()
-        .as_bytes()
-        .iter()
-        .take(7)
-        .fold(0, |acc, &ch| acc * 10 + (ch as u8 - b'0') as usize);
-    let input_len = input.trim().as_bytes().len();
-    let mut values = std::iter::repeat(input.trim().as_bytes().iter())
-        .flatten()
-        .take(10000 * input_len)
-        .skip(offset)
-        .map(|&ch| (ch as u8 - b'0') as i64)
-        .collect::<Vec<_>>();
-    for step in 0..100 {
-        let mut new_values = vec![0; values.len()];
-        let mut sum = 0;
-        for i in (0..values.len()).rev() {
-            sum += values[i];
-            sum = sum.abs() % 10;
-            new_values[i] = sum;
-        }
-        values = new_values;
-    }
-    let mut result = 0;
-    for i in 0..8 {
-        result *= 10;
-        result += values[i];
-    }
-    result
+    let offset = number(&parse(input)[..7]) as usize;
+    number(
+        &Transform::default()
+            .with_repeat(10000)
+            .with_offset(offset)
+            .run(input),
+    )
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
     use super::*;
 
     #[test]
@@ -82,4 +308,71 @@ mod tests {
         assert_eq!(part2("02935109699940807407585447034323"), 78725270);
         assert_eq!(part2("03081770884921959731165446850517"), 53553731);
     }
+
+    #[test]
+    fn test_day16_transform() {
+        let transform = Transform::default().with_phases(4).with_length(8);
+        assert_eq!(transform.run("12345678"), vec![0, 1, 0, 2, 9, 4, 9, 8]);
+        let tail = transform.clone().with_offset(5).with_length(3);
+        assert_eq!(tail.method(8), Method::SuffixSum);
+        assert_eq!(tail.run("12345678"), vec![4, 9, 8]);
+        assert_eq!(transform.method(8), Method::PrefixSum);
+
+        let signal = "80871224585914546619083218645595";
+        let full = Transform::default()
+            .with_phases(7)
+            .with_repeat(3)
+            .with_length(96);
+        let expected = full.run(signal);
+        for offset in [48, 60, 90] {
+            let window = full.clone().with_offset(offset).with_length(96 - offset);
+            assert_eq!(window.method(96), Method::SuffixSum);
+            assert_eq!(window.run(signal), expected[offset..]);
+        }
+
+        let custom = Transform::new(&[0, 2, -1]).with_phases(3);
+        let expected = custom.clone().with_length(12).run("123456789012");
+        let tail = custom.with_offset(6).with_length(6);
+        assert_eq!(tail.method(12), Method::SuffixSum);
+        assert_eq!(tail.run("123456789012"), expected[6..]);
+        assert_eq!(
+            Transform::new(&[1, -1])
+                .with_phases(1)
+                .with_length(2)
+                .run("12"),
+            vec![1, 1]
+        );
+    }
+
+    #[test]
+    fn test_day16_prefix_sum() {
+        let mut rng = StdRng::seed_from_u64(16);
+        let patterns: [&[i64]; 4] = [&[0, 1, 0, -1], &[1, -2, 3], &[0, 3], &[2]];
+        for pattern in patterns {
+            for _ in 0..10 {
+                let len = rng.gen_range(1..40);
+                let input = (0..len)
+                    .map(|_| char::from(b'0' + rng.gen_range(0..10)))
+                    .collect::<String>();
+                let repeat = rng.gen_range(1..4);
+                let offset = rng.gen_range(0..len * repeat);
+                let transform = Transform::new(pattern)
+                    .with_phases(rng.gen_range(1..6))
+                    .with_repeat(repeat)
+                    .with_offset(offset)
+                    .with_length(len * repeat - offset);
+                let expected = transform.clone().with_method(Method::Full).run(&input);
+                assert_eq!(
+                    transform.clone().with_method(Method::PrefixSum).run(&input),
+                    expected
+                );
+                let threaded = transform.with_threads(3);
+                assert_eq!(
+                    threaded.clone().with_method(Method::PrefixSum).run(&input),
+                    expected
+                );
+                assert_eq!(threaded.with_method(Method::Full).run(&input), expected);
+            }
+        }
+    }
 }
@@ -1,53 +1,372 @@
-use std::collections::HashMap;
-
-fn parse(input: &str) -> HashMap<&str, Vec<&str>> {
-    input
-        .lines()
-        .fold(HashMap::new(), |mut map: HashMap<&str, Vec<&str>>, line| {
-            let mut iter = line.trim().split(")");
-            let first = iter.next().unwrap();
-            let second = iter.next().unwrap();
-            map.entry(first).or_default().push(second);
-            map
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    io::BufRead,
+    sync::{Arc, OnceLock},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OrbitError {
+    Io(String),
+    InvalidLine { line: usize },
+    MultipleParents { body: String, line: usize },
+    MultipleRoots(Vec<String>),
+    Cycle(String),
+    UnknownBody(String),
+}
+
+impl Display for OrbitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrbitError::Io(message) => write!(f, "read error: {}", message),
+            OrbitError::InvalidLine { line } => write!(f, "line {}: expected A)B", line),
+            OrbitError::MultipleParents { body, line } => {
+                write!(f, "line {}: {} already orbits another body", line, body)
+            }
+            OrbitError::MultipleRoots(roots) => {
+                write!(f, "expected one root, found: {}", roots.join(", "))
+            }
+            OrbitError::Cycle(body) => write!(f, "orbit cycle through {}", body),
+            OrbitError::UnknownBody(body) => write!(f, "unknown body {}", body),
+        }
+    }
+}
+
+impl std::error::Error for OrbitError {}
+
+#[derive(Debug)]
+pub struct OrbitMap {
+    names: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, usize>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    depths: Vec<usize>,
+    ancestors: OnceLock<Vec<Vec<usize>>>,
+    sizes: Vec<usize>,
+}
+
+impl OrbitMap {
+    pub fn parse(input: &str) -> Result<Self, OrbitError> {
+        Self::from_reader(input.as_bytes())
+    }
+
+    pub fn from_reader(mut reader: impl BufRead) -> Result<Self, OrbitError> {
+        let mut map = OrbitMap {
+            names: vec![],
+            ids: HashMap::new(),
+            parents: vec![],
+            children: vec![],
+            depths: vec![],
+            ancestors: OnceLock::new(),
+            sizes: vec![],
+        };
+        let mut buffer = String::new();
+        for i in 0.. {
+            buffer.clear();
+            match reader.read_line(&mut buffer) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) => return Err(OrbitError::Io(err.to_string())),
+            }
+            let line = buffer.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (center, body) = line
+                .split_once(')')
+                .filter(|(center, body)| !center.is_empty() && !body.is_empty())
+                .ok_or(OrbitError::InvalidLine { line: i + 1 })?;
+            let center = map.intern(center);
+            let body = map.intern(body);
+            if map.parents[body].is_some() {
+                return Err(OrbitError::MultipleParents {
+                    body: map.names[body].to_string(),
+                    line: i + 1,
+                });
+            }
+            map.parents[body] = Some(center);
+            map.children[center].push(body);
+        }
+        map.index()?;
+        Ok(map)
+    }
+
+    fn intern(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        let name: Arc<str> = name.into();
+        self.names.push(name.clone());
+        self.ids.insert(name, id);
+        self.parents.push(None);
+        self.children.push(vec![]);
+        id
+    }
+
+    fn index(&mut self) -> Result<(), OrbitError> {
+        let n = self.names.len();
+        let roots = (0..n)
+            .filter(|&id| self.parents[id].is_none())
+            .collect::<Vec<_>>();
+        if roots.len() > 1 {
+            return Err(OrbitError::MultipleRoots(
+                roots.iter().map(|&id| self.names[id].to_string()).collect(),
+            ));
+        }
+
+        let mut order = Vec::with_capacity(n);
+        self.depths = vec![0; n];
+        let mut queue = roots.into_iter().collect::<VecDeque<_>>();
+        while let Some(id) = queue.pop_front() {
+            order.push(id);
+            for &child in &self.children[id] {
+                self.depths[child] = self.depths[id] + 1;
+                queue.push_back(child);
+            }
+        }
+        if order.len() < n {
+            let mut visited = vec![false; n];
+            order.iter().for_each(|&id| visited[id] = true);
+            let mut id = (0..n).find(|&id| !visited[id]).unwrap();
+            while !visited[id] {
+                visited[id] = true;
+                id = self.parents[id].unwrap();
+            }
+            return Err(OrbitError::Cycle(self.names[id].to_string()));
+        }
+
+        self.sizes = vec![1; n];
+        for &id in order.iter().rev() {
+            if let Some(parent) = self.parents[id] {
+                self.sizes[parent] += self.sizes[id];
+            }
+        }
+        Ok(())
+    }
+
+    fn id(&self, name: &str) -> Result<usize, OrbitError> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| OrbitError::UnknownBody(name.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn parent(&self, name: &str) -> Result<Option<&str>, OrbitError> {
+        Ok(self.parents[self.id(name)?].map(|id| &*self.names[id]))
+    }
+
+    pub fn depth(&self, name: &str) -> Result<usize, OrbitError> {
+        Ok(self.depths[self.id(name)?])
+    }
+
+    pub fn subtree_size(&self, name: &str) -> Result<usize, OrbitError> {
+        Ok(self.sizes[self.id(name)?])
+    }
+
+    pub fn total_orbits(&self) -> usize {
+        self.depths.iter().sum()
+    }
+
+    fn ancestors(&self) -> &[Vec<usize>] {
+        self.ancestors.get_or_init(|| {
+            let n = self.len();
+            let levels = (usize::BITS - n.leading_zeros()).max(1) as usize;
+            let mut ancestors = vec![(0..n).map(|id| self.parents[id].unwrap_or(id)).collect()];
+            for k in 1..levels {
+                let prev: &Vec<usize> = &ancestors[k - 1];
+                let next = (0..n).map(|id| prev[prev[id]]).collect();
+                ancestors.push(next);
+            }
+            ancestors
         })
+    }
+
+    fn lift(&self, mut id: usize, mut steps: usize) -> usize {
+        let ancestors = self.ancestors();
+        let mut k = 0;
+        while steps > 0 {
+            if steps & 1 == 1 {
+                id = ancestors[k][id];
+            }
+            steps >>= 1;
+            k += 1;
+        }
+        id
+    }
+
+    // `lca` uses binary lifting: O(log n) per query once the O(n log n)
+    // table has been built. Distance, path and transfer queries climb parent
+    // links instead, which is O(depth) per query and allocates no table.
+    fn lca_id(&self, a: usize, b: usize) -> usize {
+        let (mut a, mut b) = if self.depths[a] >= self.depths[b] {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        a = self.lift(a, self.depths[a] - self.depths[b]);
+        if a == b {
+            return a;
+        }
+        for level in self.ancestors().iter().rev() {
+            if level[a] != level[b] {
+                a = level[a];
+                b = level[b];
+            }
+        }
+        self.ancestors()[0][a]
+    }
+
+    fn climb_lca(&self, mut a: usize, mut b: usize) -> usize {
+        while self.depths[a] > self.depths[b] {
+            a = self.parents[a].unwrap();
+        }
+        while self.depths[b] > self.depths[a] {
+            b = self.parents[b].unwrap();
+        }
+        while a != b {
+            a = self.parents[a].unwrap();
+            b = self.parents[b].unwrap();
+        }
+        a
+    }
+
+    pub fn lca(&self, a: &str, b: &str) -> Result<&str, OrbitError> {
+        let lca = self.lca_id(self.id(a)?, self.id(b)?);
+        Ok(&self.names[lca])
+    }
+
+    pub fn distance(&self, a: &str, b: &str) -> Result<usize, OrbitError> {
+        let (a, b) = (self.id(a)?, self.id(b)?);
+        let lca = self.climb_lca(a, b);
+        Ok(self.depths[a] + self.depths[b] - 2 * self.depths[lca])
+    }
+
+    fn path_ids(&self, a: usize, b: usize) -> Vec<usize> {
+        let lca = self.climb_lca(a, b);
+        let climb = |mut id: usize| {
+            let mut path = vec![id];
+            while id != lca {
+                id = self.parents[id].unwrap();
+                path.push(id);
+            }
+            path
+        };
+        let mut path = climb(a);
+        let mut back = climb(b);
+        back.pop();
+        path.extend(back.into_iter().rev());
+        path
+    }
+
+    pub fn path(&self, a: &str, b: &str) -> Result<Vec<&str>, OrbitError> {
+        Ok(self
+            .path_ids(self.id(a)?, self.id(b)?)
+            .into_iter()
+            .map(|id| &*self.names[id])
+            .collect())
+    }
+
+    fn highlighted(&self, highlight: Option<(&str, &str)>) -> Result<Vec<bool>, OrbitError> {
+        let mut marked = vec![false; self.len()];
+        if let Some((a, b)) = highlight {
+            for id in self.path_ids(self.id(a)?, self.id(b)?) {
+                marked[id] = true;
+            }
+        }
+        Ok(marked)
+    }
+
+    fn root(&self) -> Option<usize> {
+        (0..self.len()).find(|&id| self.parents[id].is_none())
+    }
+
+    pub fn to_dot(&self, highlight: Option<(&str, &str)>) -> Result<String, OrbitError> {
+        let marked = self.highlighted(highlight)?;
+        let mut lines = vec!["digraph orbits {".to_string()];
+        let mut stack = self.root().into_iter().collect::<Vec<_>>();
+        while let Some(id) = stack.pop() {
+            if marked[id] {
+                lines.push(format!(
+                    "    \"{}\" [color=red, style=bold];",
+                    self.names[id]
+                ));
+            }
+            for &child in &self.children[id] {
+                let style = if marked[id] && marked[child] {
+                    " [color=red, penwidth=2]"
+                } else {
+                    ""
+                };
+                lines.push(format!(
+                    "    \"{}\" -> \"{}\"{};",
+                    self.names[id], self.names[child], style
+                ));
+            }
+            stack.extend(self.children[id].iter().rev());
+        }
+        lines.push("}".to_string());
+        Ok(lines.join("\n"))
+    }
+
+    pub fn to_ascii_tree(&self, highlight: Option<(&str, &str)>) -> Result<String, OrbitError> {
+        let marked = self.highlighted(highlight)?;
+        let label = |id: usize| {
+            if marked[id] {
+                format!("[{}]", self.names[id])
+            } else {
+                self.names[id].to_string()
+            }
+        };
+        let mut lines = vec![];
+        let mut stack = self
+            .root()
+            .map(|root| (root, label(root), String::new()))
+            .into_iter()
+            .collect::<Vec<_>>();
+        while let Some((id, line, prefix)) = stack.pop() {
+            lines.push(line);
+            let children = &self.children[id];
+            for (i, &child) in children.iter().enumerate().rev() {
+                let (branch, indent) = if i + 1 == children.len() {
+                    ("└── ", "    ")
+                } else {
+                    ("├── ", "│   ")
+                };
+                stack.push((
+                    child,
+                    format!("{}{}{}", prefix, branch, label(child)),
+                    format!("{}{}", prefix, indent),
+                ));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    pub fn transfers(&self, from: &str, to: &str) -> Result<usize, OrbitError> {
+        let from = self.parent(from)?.unwrap_or(from);
+        let to = self.parent(to)?.unwrap_or(to);
+        self.distance(from, to)
+    }
 }
 
 pub fn part1(input: &str) -> i64 {
-    let map = parse(input);
-    let mut queue: Vec<(&str, i64)> = vec![("COM", 0)];
-    let mut result = 0;
-    while let Some((name, depth)) = queue.pop() {
-        result += depth;
-        map.get(name).unwrap_or(&vec![]).iter().for_each(|x| {
-            queue.push((x, depth + 1));
-        });
-    }
-    result
+    OrbitMap::parse(input).unwrap().total_orbits() as i64
 }
 
 pub fn part2(input: &str) -> i64 {
-    let map = parse(input);
-    let mut queue: Vec<(&str, i64, Vec<&str>)> = vec![("COM", 0, vec!["COM"])];
-    let mut you: Vec<&str> = vec![];
-    let mut san: Vec<&str> = vec![];
-    while let Some((name, depth, path)) = queue.pop() {
-        if name == "YOU" {
-            you = path.clone();
-        } else if name == "SAN" {
-            san = path.clone();
-        }
-        map.get(name).unwrap_or(&vec![]).iter().for_each(|x| {
-            let mut new_path = path.clone();
-            new_path.push(x);
-            queue.push((x, depth + 1, new_path));
-        });
-    }
-    for i in 0..you.len().min(san.len()) {
-        if you[i] != san[i] {
-            return (you.len() - i + san.len() - i) as i64 - 2;
-        }
-    }
-    0
+    OrbitMap::parse(input)
+        .unwrap()
+        .transfers("YOU", "SAN")
+        .unwrap() as i64
 }
 
 #[cfg(test)]
@@ -89,4 +408,116 @@ mod tests {
     fn test_day6_part2() {
         assert_eq!(part2(INPUT2), 4);
     }
+
+    #[test]
+    fn test_day6_orbit_map() {
+        let map = OrbitMap::parse(INPUT2).unwrap();
+        assert_eq!(map.len(), 14);
+        assert_eq!(map.depth("L"), Ok(7));
+        assert_eq!(map.parent("COM"), Ok(None));
+        assert_eq!(map.lca("YOU", "SAN"), Ok("D"));
+        assert_eq!(map.lca("H", "L"), Ok("B"));
+        assert_eq!(map.lca("E", "L"), Ok("E"));
+        assert_eq!(map.distance("H", "F"), Ok(6));
+        assert_eq!(map.subtree_size("COM"), Ok(14));
+        assert_eq!(map.subtree_size("E"), Ok(6));
+        assert_eq!(
+            map.transfers("YOU", "X"),
+            Err(OrbitError::UnknownBody("X".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_day6_orbit_errors() {
+        assert_eq!(
+            OrbitMap::parse("COM)A\nB)C").unwrap_err(),
+            OrbitError::MultipleRoots(vec!["COM".to_string(), "B".to_string()])
+        );
+        assert_eq!(
+            OrbitMap::parse("COM)A\nA)B\nC)D\nD)C").unwrap_err(),
+            OrbitError::Cycle("C".to_string())
+        );
+        assert_eq!(
+            OrbitMap::parse("COM)A\nB)A").unwrap_err(),
+            OrbitError::MultipleParents {
+                body: "A".to_string(),
+                line: 2
+            }
+        );
+        assert_eq!(
+            OrbitMap::parse("COM)A\nB").unwrap_err(),
+            OrbitError::InvalidLine { line: 2 }
+        );
+    }
+
+    #[test]
+    fn test_day6_render() {
+        let map = OrbitMap::parse(INPUT2).unwrap();
+        assert_eq!(
+            map.path("YOU", "SAN"),
+            Ok(vec!["YOU", "K", "J", "E", "D", "I", "SAN"])
+        );
+        assert_eq!(
+            map.to_ascii_tree(Some(("YOU", "SAN"))).unwrap(),
+            "COM
+└── B
+    ├── C
+    │   └── [D]
+    │       ├── [E]
+    │       │   ├── F
+    │       │   └── [J]
+    │       │       └── [K]
+    │       │           ├── L
+    │       │           └── [YOU]
+    │       └── [I]
+    │           └── [SAN]
+    └── G
+        └── H"
+        );
+
+        let map = OrbitMap::parse("COM)B\nB)C\nB)D").unwrap();
+        assert_eq!(
+            map.to_dot(Some(("C", "B"))).unwrap(),
+            "digraph orbits {
+    \"COM\" -> \"B\";
+    \"B\" [color=red, style=bold];
+    \"B\" -> \"C\" [color=red, penwidth=2];
+    \"B\" -> \"D\";
+    \"C\" [color=red, style=bold];
+}"
+        );
+        assert!(map.to_dot(Some(("C", "X"))).is_err());
+    }
+
+    #[test]
+    fn test_day6_streaming() {
+        let n = 100_000;
+        let input = (0..n)
+            .map(|i| {
+                format!(
+                    "N{}){}",
+                    i,
+                    if i + 1 == n {
+                        "YOU".to_string()
+                    } else {
+                        format!("N{}", i + 1)
+                    }
+                )
+            })
+            .chain(["N10)SAN".to_string()])
+            .collect::<Vec<_>>()
+            .join("\n");
+        let map = OrbitMap::from_reader(std::io::BufReader::new(input.as_bytes())).unwrap();
+        assert_eq!(map.len(), n + 2);
+        assert_eq!(map.total_orbits(), n * (n + 1) / 2 + 11);
+        assert_eq!(map.transfers("YOU", "SAN"), Ok(n - 11));
+        assert!(map.ancestors.get().is_none());
+        assert_eq!(map.lca("YOU", "SAN"), Ok("N10"));
+        assert!(map.ancestors.get().is_some());
+
+        fn send_sync<T: Send + Sync>(_: &T) {}
+        send_sync(&map);
+        let total = std::thread::scope(|scope| scope.spawn(|| map.total_orbits()).join().unwrap());
+        assert_eq!(total, map.total_orbits());
+    }
 }
@@ -1,48 +1,367 @@
-fn decode(input: &str, width: usize, height: usize) -> String {
-    let mut image = vec![vec![b'2'; width]; height];
-    input
-        .trim()
-        .as_bytes()
-        .chunks(width * height)
-        .fold(&mut image, |image, chunk| {
-            chunk.iter().enumerate().for_each(|(i, &ch)| {
-                let x = i % width;
-                let y = i / width;
-                if image[y][x] == b'2' && ch != b'2' {
-                    image[y][x] = if ch == b'0' { b' ' } else { b'#' };
+use std::fmt::Display;
+
+use crate::ocr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pixel {
+    Black,
+    White,
+    Transparent,
+}
+
+impl Pixel {
+    fn from_digit(ch: u8) -> Option<Self> {
+        match ch {
+            b'0' => Some(Pixel::Black),
+            b'1' => Some(Pixel::White),
+            b'2' => Some(Pixel::Transparent),
+            _ => None,
+        }
+    }
+
+    fn digit(self) -> char {
+        match self {
+            Pixel::Black => '0',
+            Pixel::White => '1',
+            Pixel::Transparent => '2',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layer {
+    pixels: Vec<Pixel>,
+}
+
+impl Layer {
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.pixels
+    }
+
+    pub fn count(&self, pixel: Pixel) -> usize {
+        self.pixels.iter().filter(|&&p| p == pixel).count()
+    }
+
+    pub fn counts(&self) -> [usize; 3] {
+        [
+            self.count(Pixel::Black),
+            self.count(Pixel::White),
+            self.count(Pixel::Transparent),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub black: [u8; 3],
+    pub white: [u8; 3],
+    pub transparent: [u8; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            black: [0, 0, 0],
+            white: [255, 255, 255],
+            transparent: [128, 128, 128],
+        }
+    }
+}
+
+impl Palette {
+    fn color(&self, pixel: Pixel) -> [u8; 3] {
+        match pixel {
+            Pixel::Black => self.black,
+            Pixel::White => self.white,
+            Pixel::Transparent => self.transparent,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SifError {
+    Empty,
+    InvalidDimensions {
+        width: usize,
+        height: usize,
+    },
+    InvalidPixel {
+        layer: usize,
+        pixel: usize,
+        ch: char,
+    },
+    PartialLayer {
+        layer: usize,
+        pixels: usize,
+        expected: usize,
+    },
+}
+
+impl Display for SifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SifError::Empty => write!(f, "empty image"),
+            SifError::InvalidDimensions { width, height } => {
+                write!(f, "invalid dimensions {}x{}", width, height)
+            }
+            SifError::InvalidPixel { layer, pixel, ch } => {
+                write!(
+                    f,
+                    "invalid pixel {:?} in layer {} at index {}",
+                    ch, layer, pixel
+                )
+            }
+            SifError::PartialLayer {
+                layer,
+                pixels,
+                expected,
+            } => write!(
+                f,
+                "layer {} has {} pixels, expected {}",
+                layer, pixels, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SifError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    Sif(SifError),
+    Ocr(ocr::OcrError),
+}
+
+impl From<SifError> for DecodeError {
+    fn from(err: SifError) -> Self {
+        DecodeError::Sif(err)
+    }
+}
+
+impl From<ocr::OcrError> for DecodeError {
+    fn from(err: ocr::OcrError) -> Self {
+        DecodeError::Ocr(err)
+    }
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Sif(err) => write!(f, "invalid image: {}", err),
+            DecodeError::Ocr(err) => write!(f, "unreadable image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SifImage {
+    width: usize,
+    height: usize,
+    layers: Vec<Layer>,
+}
+
+impl SifImage {
+    pub fn parse(input: &str, width: usize, height: usize) -> Result<Self, SifError> {
+        let data = input.trim().as_bytes();
+        let size = width * height;
+        if size == 0 {
+            return Err(SifError::InvalidDimensions { width, height });
+        }
+        if data.is_empty() {
+            return Err(SifError::Empty);
+        }
+        let layers = data
+            .chunks(size)
+            .enumerate()
+            .map(|(layer, chunk)| {
+                let pixels = chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(pixel, &ch)| {
+                        Pixel::from_digit(ch).ok_or(SifError::InvalidPixel {
+                            layer,
+                            pixel,
+                            ch: ch as char,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if pixels.len() != size {
+                    return Err(SifError::PartialLayer {
+                        layer,
+                        pixels: pixels.len(),
+                        expected: size,
+                    });
                 }
-            });
-            image
+                Ok(Layer { pixels })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            width,
+            height,
+            layers,
         })
-        .iter()
-        .map(|row| std::str::from_utf8(row).unwrap())
-        .collect::<Vec<_>>()
-        .join("\n")
-}
-
-pub fn part1(input: &str) -> i64 {
-    input
-        .trim()
-        .as_bytes()
-        .chunks(25 * 6)
-        .map(|chunk| {
-            chunk.iter().fold((0, 0, 0), |mut acc, &ch| {
-                match ch {
-                    b'0' => acc.0 += 1,
-                    b'1' => acc.1 += 1,
-                    b'2' => acc.2 += 1,
-                    _ => (),
+    }
+
+    pub fn guess_dimensions(input: &str) -> Result<Vec<(usize, usize)>, SifError> {
+        let len = input.trim().len();
+        if len == 0 {
+            return Err(SifError::Empty);
+        }
+        let mut candidates = vec![];
+        for size in (1..=len).filter(|&size| len.is_multiple_of(size)) {
+            for height in (1..=size).filter(|&height| size.is_multiple_of(height)) {
+                let image = SifImage::parse(input, size / height, height)?;
+                let composite = image.composite();
+                if composite.count(Pixel::Transparent) > 0 {
+                    continue;
                 }
-                acc
+                let readable = ocr::recognize(&image.to_ascii()).is_ok_and(|text| !text.is_empty());
+                candidates.push((readable, image.layers.len() > 1, image.width, image.height));
+            }
+        }
+        candidates.sort_by(|a, b| b.cmp(a));
+        Ok(candidates
+            .into_iter()
+            .map(|(_, _, width, height)| (width, height))
+            .collect())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn composite(&self) -> Layer {
+        let mut pixels = vec![Pixel::Transparent; self.width * self.height];
+        for layer in &self.layers {
+            for (pixel, &value) in pixels.iter_mut().zip(&layer.pixels) {
+                if *pixel == Pixel::Transparent {
+                    *pixel = value;
+                }
+            }
+        }
+        Layer { pixels }
+    }
+
+    pub fn encode(&self) -> String {
+        self.layers
+            .iter()
+            .flat_map(|layer| layer.pixels.iter().map(|pixel| pixel.digit()))
+            .collect()
+    }
+
+    pub fn to_ascii(&self) -> String {
+        self.composite()
+            .pixels
+            .chunks(self.width)
+            .map(|row| {
+                row.iter()
+                    .map(|pixel| match pixel {
+                        Pixel::Black => ' ',
+                        Pixel::White => '#',
+                        Pixel::Transparent => '2',
+                    })
+                    .collect::<String>()
             })
-        })
-        .min()
-        .map(|(_, x, y)| x * y)
-        .unwrap()
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn to_pgm(&self, palette: &Palette) -> Vec<u8> {
+        let mut data = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        data.extend(self.composite().pixels.iter().map(|&pixel| {
+            let [r, g, b] = palette.color(pixel);
+            ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+        }));
+        data
+    }
+
+    pub fn to_png(&self, palette: &Palette) -> Vec<u8> {
+        let mut raw = vec![];
+        for row in self.composite().pixels.chunks(self.width) {
+            raw.push(0);
+            raw.extend(row.iter().flat_map(|&pixel| palette.color(pixel)));
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let blocks = raw.chunks(0xffff).collect::<Vec<_>>();
+        for (i, block) in blocks.iter().enumerate() {
+            let len = block.len() as u16;
+            zlib.push((i + 1 == blocks.len()) as u8);
+            zlib.extend(len.to_le_bytes());
+            zlib.extend((!len).to_le_bytes());
+            zlib.extend(*block);
+        }
+        if blocks.is_empty() {
+            zlib.extend([1, 0, 0, 0xff, 0xff]);
+        }
+        zlib.extend(adler32(&raw).to_be_bytes());
+
+        let mut header = vec![];
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        png_chunk(&mut data, b"IHDR", &header);
+        png_chunk(&mut data, b"IDAT", &zlib);
+        png_chunk(&mut data, b"IEND", &[]);
+        data
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |mut crc, &byte| {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
 }
 
-pub fn part2(input: &str) -> String {
-    decode(input, 25, 6)
+fn png_chunk(data: &mut Vec<u8>, kind: &[u8; 4], content: &[u8]) {
+    data.extend((content.len() as u32).to_be_bytes());
+    let start = data.len();
+    data.extend(kind);
+    data.extend(content);
+    let crc = crc32(&data[start..]);
+    data.extend(crc.to_be_bytes());
+}
+
+fn decode(input: &str, width: usize, height: usize) -> Result<String, SifError> {
+    Ok(SifImage::parse(input, width, height)?.to_ascii())
+}
+
+pub fn part1(input: &str) -> Result<i64, SifError> {
+    let image = SifImage::parse(input, 25, 6)?;
+    let [_, ones, twos] = image.layers().iter().map(Layer::counts).min().unwrap();
+    Ok((ones * twos) as i64)
+}
+
+pub fn part2(input: &str) -> Result<String, DecodeError> {
+    Ok(ocr::recognize(&decode(input, 25, 6)?)?)
 }
 
 #[cfg(test)]
@@ -50,10 +369,118 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_day8_part1() {}
+    fn test_day8_part1() {
+        let input =
+            "0".repeat(75) + &"1".repeat(50) + &"2".repeat(25) + &"0".repeat(100) + &"1".repeat(50);
+        assert_eq!(part1(&input), Ok(50 * 25));
+        assert_eq!(
+            part1("012"),
+            Err(SifError::PartialLayer {
+                layer: 0,
+                pixels: 3,
+                expected: 150
+            })
+        );
+    }
+
+    const LETTERS: [&str; 6] = [
+        ".##..####.#....#..#.#....",
+        "#..#.#....#....#..#.#....",
+        "#....###..#....#..#.#....",
+        "#....#....#....#..#.#....",
+        "#..#.#....#....#..#.#....",
+        ".##..#....####..##..####.",
+    ];
 
     #[test]
     fn test_day8_part2() {
-        assert_eq!(decode("0222112222120000", 2, 2), String::from(" #\n# "));
+        assert_eq!(decode("0222112222120000", 2, 2), Ok(String::from(" #\n# ")));
+
+        let input = "2".repeat(25 * 6) + &LETTERS.concat().replace('#', "1").replace('.', "0");
+        assert_eq!(part2(&input), Ok("CFLUL".to_string()));
+        let input = "2".repeat(25 * 6) + &"1".repeat(25 * 6);
+        assert!(matches!(
+            part2(&input),
+            Err(DecodeError::Ocr(ocr::OcrError::UnknownGlyph {
+                column: 0,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            part2("012"),
+            Err(DecodeError::Sif(SifError::PartialLayer { layer: 0, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_day8_image() {
+        let image = SifImage::parse("0222112222120000", 2, 2).unwrap();
+        assert_eq!(image.layers().len(), 4);
+        assert_eq!(image.layers()[0].counts(), [1, 0, 3]);
+        assert_eq!(
+            image.composite().pixels(),
+            [Pixel::Black, Pixel::White, Pixel::White, Pixel::Black]
+        );
+        assert_eq!(image.encode(), "0222112222120000");
+        assert_eq!(SifImage::parse("2221", 2, 1).unwrap().to_ascii(), "2#");
+
+        let palette = Palette {
+            white: [255, 0, 0],
+            ..Palette::default()
+        };
+        assert_eq!(image.to_pgm(&palette), b"P5\n2 2\n255\n\x00\x4c\x4c\x00");
+
+        let png = image.to_png(&palette);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], [0, 0, 0, 2, 0, 0, 0, 2]);
+        assert_eq!(&png[png.len() - 8..], b"IEND\xae\x42\x60\x82");
+        let idat = &png[41..png.len() - 16];
+        assert_eq!(
+            &idat[..idat.len() - 4],
+            [
+                0x78, 1, 1, 14, 0, 241, 255, 0, 0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 0, 0
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day8_validation() {
+        assert_eq!(SifImage::parse(" \n", 2, 2), Err(SifError::Empty));
+        assert_eq!(
+            SifImage::parse("0122012", 2, 2),
+            Err(SifError::PartialLayer {
+                layer: 1,
+                pixels: 3,
+                expected: 4
+            })
+        );
+        assert_eq!(
+            SifImage::parse("01220132", 2, 2),
+            Err(SifError::InvalidPixel {
+                layer: 1,
+                pixel: 2,
+                ch: '3'
+            })
+        );
+        assert_eq!(
+            SifImage::parse("0122", 0, 2),
+            Err(SifError::InvalidDimensions {
+                width: 0,
+                height: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_day8_guess_dimensions() {
+        let input = "2".repeat(25 * 6) + &LETTERS.concat().replace('#', "1").replace('.', "0");
+        assert_eq!(SifImage::guess_dimensions(&input).unwrap()[0], (25, 6));
+        assert_eq!(
+            SifImage::guess_dimensions("0222112222120000").unwrap()[..2],
+            [(4, 1), (2, 2)]
+        );
+        assert!(SifImage::guess_dimensions("01x2").is_err());
+        assert_eq!(SifImage::guess_dimensions(" \n"), Err(SifError::Empty));
     }
 }
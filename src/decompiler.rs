@@ -0,0 +1,668 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Param {
+    Position(i64),
+    Immediate(i64),
+    Relative(i64),
+}
+
+#[derive(Debug, Clone)]
+struct Instruction {
+    addr: usize,
+    op: i64,
+    params: Vec<Param>,
+}
+
+impl Instruction {
+    fn next(&self) -> usize {
+        self.addr + self.params.len() + 1
+    }
+
+    fn flow(&self) -> Flow {
+        match (self.op, self.params.as_slice()) {
+            (99, _) => Flow::Halt,
+            (5, [Param::Immediate(0), _]) => Flow::Next,
+            (6, [Param::Immediate(value), _]) if *value != 0 => Flow::Next,
+            (5 | 6, [Param::Immediate(_), target]) => Flow::Jump(Target::from(*target)),
+            (5, [value, target]) => Flow::Branch(Cond::NonZero(*value), Target::from(*target)),
+            (6, [value, target]) => Flow::Branch(Cond::Zero(*value), Target::from(*target)),
+            _ => Flow::Next,
+        }
+    }
+
+    fn constant_store(&self) -> Option<(i64, Param)> {
+        match (self.op, self.params.as_slice()) {
+            (1, [Param::Immediate(a), Param::Immediate(b), dst]) => Some((a + b, *dst)),
+            (2, [Param::Immediate(a), Param::Immediate(b), dst]) => Some((a * b, *dst)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target {
+    Static(usize),
+    Dynamic(Param),
+}
+
+impl From<Param> for Target {
+    fn from(param: Param) -> Self {
+        match param {
+            Param::Immediate(addr) if addr >= 0 => Target::Static(addr as usize),
+            _ => Target::Dynamic(param),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cond {
+    NonZero(Param),
+    Zero(Param),
+    Less(Param, Param),
+    NotLess(Param, Param),
+    Equal(Param, Param),
+    NotEqual(Param, Param),
+}
+
+impl Cond {
+    fn negate(self) -> Self {
+        match self {
+            Cond::NonZero(a) => Cond::Zero(a),
+            Cond::Zero(a) => Cond::NonZero(a),
+            Cond::Less(a, b) => Cond::NotLess(a, b),
+            Cond::NotLess(a, b) => Cond::Less(a, b),
+            Cond::Equal(a, b) => Cond::NotEqual(a, b),
+            Cond::NotEqual(a, b) => Cond::Equal(a, b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Flow {
+    Next,
+    Halt,
+    Jump(Target),
+    Branch(Cond, Target),
+}
+
+#[derive(Debug)]
+struct Call {
+    callee: usize,
+    slot: i64,
+    args: Vec<Param>,
+}
+
+fn decode(program: &[i64], addr: usize) -> Option<Instruction> {
+    let code = *program.get(addr)?;
+    let op = code % 100;
+    let count = match op {
+        1 | 2 | 7 | 8 => 3,
+        3 | 4 | 9 => 1,
+        5 | 6 => 2,
+        99 => 0,
+        _ => return None,
+    };
+    let mut modes = code / 100;
+    let mut params = vec![];
+    for i in 0..count {
+        let value = *program.get(addr + i + 1)?;
+        params.push(match modes % 10 {
+            0 => Param::Position(value),
+            1 => Param::Immediate(value),
+            2 => Param::Relative(value),
+            _ => return None,
+        });
+        modes /= 10;
+    }
+    let writes = matches!(op, 1 | 2 | 3 | 7 | 8);
+    if writes && matches!(params.last(), Some(Param::Immediate(_))) {
+        return None;
+    }
+    Some(Instruction { addr, op, params })
+}
+
+fn detect_call(program: &[i64], jump: &Instruction) -> Option<Call> {
+    let Flow::Jump(Target::Static(callee)) = jump.flow() else {
+        return None;
+    };
+    let store = decode(program, jump.addr.checked_sub(4)?)?;
+    let (value, Param::Relative(slot)) = store.constant_store()? else {
+        return None;
+    };
+    if value != jump.next() as i64 {
+        return None;
+    }
+
+    let mut args = BTreeMap::new();
+    let mut addr = store.addr;
+    while let Some(prev) = addr.checked_sub(4).and_then(|a| decode(program, a)) {
+        match (prev.op, prev.params.as_slice()) {
+            (1, [a, Param::Immediate(0), Param::Relative(k)])
+            | (1, [Param::Immediate(0), a, Param::Relative(k)])
+            | (2, [a, Param::Immediate(1), Param::Relative(k)])
+            | (2, [Param::Immediate(1), a, Param::Relative(k)])
+                if *k > slot && !args.contains_key(k) =>
+            {
+                args.insert(*k, *a);
+                addr = prev.addr;
+            }
+            _ => break,
+        }
+    }
+    if args.keys().copied().ne(slot + 1..=slot + args.len() as i64) {
+        args.clear();
+    }
+    Some(Call {
+        callee,
+        slot,
+        args: args.into_values().collect(),
+    })
+}
+
+fn explore(
+    program: &[i64],
+    entry: usize,
+    calls: &mut HashMap<usize, Call>,
+) -> BTreeMap<usize, Instruction> {
+    let mut body = BTreeMap::new();
+    let mut stack = vec![entry];
+    while let Some(addr) = stack.pop() {
+        if body.contains_key(&addr) {
+            continue;
+        }
+        let Some(ins) = decode(program, addr) else {
+            continue;
+        };
+        match ins.flow() {
+            Flow::Halt => {}
+            Flow::Next => stack.push(ins.next()),
+            Flow::Jump(Target::Static(target)) => {
+                if let Some(call) = detect_call(program, &ins) {
+                    calls.insert(addr, call);
+                    stack.push(ins.next());
+                } else {
+                    stack.push(target);
+                }
+            }
+            Flow::Jump(Target::Dynamic(_)) => {}
+            Flow::Branch(_, target) => {
+                stack.push(ins.next());
+                if let Target::Static(target) = target {
+                    stack.push(target);
+                }
+            }
+        }
+        body.insert(addr, ins);
+    }
+    body
+}
+
+struct Function<'a> {
+    program: &'a [i64],
+    body: &'a BTreeMap<usize, Instruction>,
+    calls: &'a HashMap<usize, Call>,
+    frame: Option<i64>,
+    skip: HashSet<usize>,
+    labels: BTreeSet<usize>,
+    lines: Vec<String>,
+}
+
+impl Function<'_> {
+    fn operand(&self, param: Param) -> String {
+        match (param, self.frame) {
+            (Param::Immediate(value), _) => value.to_string(),
+            (Param::Position(addr), _) => format!("mem[{}]", addr),
+            (Param::Relative(offset), Some(frame)) if offset < 0 => {
+                format!("local{}", offset + frame)
+            }
+            (Param::Relative(offset), _) => format!("rb[{}]", offset),
+        }
+    }
+
+    fn cond(&self, cond: Cond) -> String {
+        match cond {
+            Cond::NonZero(a) => format!("{} != 0", self.operand(a)),
+            Cond::Zero(a) => format!("{} == 0", self.operand(a)),
+            Cond::Less(a, b) => format!("{} < {}", self.operand(a), self.operand(b)),
+            Cond::NotLess(a, b) => format!("{} >= {}", self.operand(a), self.operand(b)),
+            Cond::Equal(a, b) => format!("{} == {}", self.operand(a), self.operand(b)),
+            Cond::NotEqual(a, b) => format!("{} != {}", self.operand(a), self.operand(b)),
+        }
+    }
+
+    fn line(&mut self, depth: usize, text: impl Display) {
+        self.lines.push(format!("{}{}", "    ".repeat(depth), text));
+    }
+
+    fn instruction(&self, addr: usize) -> Option<&Instruction> {
+        self.body.get(&addr).filter(|_| !self.skip.contains(&addr))
+    }
+
+    // A conditional jump, optionally preceded by the comparison that produced its flag.
+    fn branch(&self, addr: usize) -> Option<(Cond, Target, usize)> {
+        let ins = self.instruction(addr)?;
+        if let Flow::Branch(cond, target) = ins.flow() {
+            return Some((cond, target, ins.next()));
+        }
+        let (compare, dst) = match (ins.op, ins.params.as_slice()) {
+            (7, [a, b, dst]) => (Cond::Less(*a, *b), *dst),
+            (8, [a, b, dst]) => (Cond::Equal(*a, *b), *dst),
+            _ => return None,
+        };
+        let jump = self.instruction(ins.next())?;
+        match jump.flow() {
+            Flow::Branch(Cond::NonZero(flag), target) if flag == dst => {
+                Some((compare, target, jump.next()))
+            }
+            Flow::Branch(Cond::Zero(flag), target) if flag == dst => {
+                Some((compare.negate(), target, jump.next()))
+            }
+            _ => None,
+        }
+    }
+
+    fn back_edge(&self, head: usize, end: usize) -> Option<usize> {
+        self.body
+            .range(head..end)
+            .rev()
+            .filter(|(addr, _)| !self.calls.contains_key(addr))
+            .find(|(_, ins)| {
+                matches!(
+                    ins.flow(),
+                    Flow::Jump(Target::Static(t)) | Flow::Branch(_, Target::Static(t)) if t == head
+                )
+            })
+            .map(|(_, ins)| ins.next())
+    }
+
+    fn goto(&mut self, target: Target) -> String {
+        match target {
+            Target::Static(addr) => {
+                self.labels.insert(addr);
+                format!("goto label_{};", addr)
+            }
+            Target::Dynamic(param) => format!("goto *{};", self.operand(param)),
+        }
+    }
+
+    fn jump(&mut self, target: Target, lp: Option<(usize, usize)>) -> String {
+        match (target, lp) {
+            (Target::Static(t), Some((head, _))) if t == head => "continue;".to_string(),
+            (Target::Static(t), Some((_, exit))) if t == exit => "break;".to_string(),
+            (Target::Dynamic(Param::Relative(_)), _) => "return;".to_string(),
+            _ => self.goto(target),
+        }
+    }
+
+    fn statement(&self, ins: &Instruction) -> String {
+        let p = |i: usize| self.operand(ins.params[i]);
+        match (ins.op, ins.params.as_slice()) {
+            (1, [a, Param::Immediate(0), _]) | (2, [a, Param::Immediate(1), _]) => {
+                format!("{} = {};", p(2), self.operand(*a))
+            }
+            (1, [Param::Immediate(0), b, _]) | (2, [Param::Immediate(1), b, _]) => {
+                format!("{} = {};", p(2), self.operand(*b))
+            }
+            (1, [_, Param::Immediate(b), _]) if *b < 0 => {
+                format!("{} = {} - {};", p(2), p(0), -b)
+            }
+            (1, _) => format!("{} = {} + {};", p(2), p(0), p(1)),
+            (2, _) => format!("{} = {} * {};", p(2), p(0), p(1)),
+            (3, _) => format!("{} = input();", p(0)),
+            (4, _) => format!("output({});", p(0)),
+            (7, _) => format!("{} = ({} < {}) as i64;", p(2), p(0), p(1)),
+            (8, _) => format!("{} = ({} == {}) as i64;", p(2), p(0), p(1)),
+            (9, _) => format!("rb += {};", p(0)),
+            (99, _) => "halt();".to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn emit(&mut self, start: usize, end: usize, depth: usize, lp: Option<(usize, usize)>) {
+        let mut pc = start;
+        while pc < end
+            && let Some((&addr, ins)) = self.body.range(pc..end).next()
+        {
+            let ins = ins.clone();
+            if self.labels.contains(&addr) && lp.is_none_or(|(head, _)| head != addr) {
+                self.line(depth.saturating_sub(1), format!("label_{}:", addr));
+            }
+            if self.skip.contains(&addr) {
+                pc = ins.next();
+                continue;
+            }
+
+            if lp.is_none_or(|(head, _)| head != addr)
+                && let Some(exit) = self.back_edge(addr, end)
+            {
+                match self.branch(addr) {
+                    Some((cond, Target::Static(t), after)) if t == exit => {
+                        let cond = self.cond(cond.negate());
+                        self.line(depth, format!("while {} {{", cond));
+                        self.emit(after, exit, depth + 1, Some((addr, exit)));
+                    }
+                    _ => {
+                        self.line(depth, "loop {");
+                        self.emit(addr, exit, depth + 1, Some((addr, exit)));
+                    }
+                }
+                self.line(depth, "}");
+                pc = exit;
+                continue;
+            }
+
+            if let Some((cond, target, after)) = self.branch(addr) {
+                let back_edge = lp.is_some_and(|(head, exit)| {
+                    after == exit && matches!(target, Target::Static(t) if t == head)
+                });
+                match target {
+                    _ if back_edge => {
+                        let cond = self.cond(cond.negate());
+                        self.line(depth, format!("if {} {{", cond));
+                        self.line(depth + 1, "break;");
+                        self.line(depth, "}");
+                    }
+                    Target::Static(t)
+                        if t > addr
+                            && t >= after
+                            && t <= end
+                            && lp.is_none_or(|(_, exit)| t != exit) =>
+                    {
+                        let cond = self.cond(cond.negate());
+                        self.line(depth, format!("if {} {{", cond));
+                        let last = self
+                            .body
+                            .range(after..t)
+                            .next_back()
+                            .map(|(_, ins)| ins.clone());
+                        let otherwise = last.as_ref().and_then(|last| match last.flow() {
+                            Flow::Jump(Target::Static(t2))
+                                if t2 > t && t2 <= end && !self.calls.contains_key(&last.addr) =>
+                            {
+                                Some((last.addr, t2))
+                            }
+                            _ => None,
+                        });
+                        if let Some((jump, t2)) = otherwise {
+                            self.emit(after, jump, depth + 1, lp);
+                            self.line(depth, "} else {");
+                            self.emit(t, t2, depth + 1, lp);
+                            pc = t2;
+                        } else {
+                            self.emit(after, t, depth + 1, lp);
+                            pc = t;
+                        }
+                        self.line(depth, "}");
+                        continue;
+                    }
+                    _ => {
+                        let cond = self.cond(cond);
+                        let jump = self.jump(target, lp);
+                        self.line(depth, format!("if {} {{", cond));
+                        self.line(depth + 1, jump);
+                        self.line(depth, "}");
+                    }
+                }
+                pc = after;
+                continue;
+            }
+
+            if let Some(call) = self.calls.get(&addr) {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|&arg| self.operand(arg))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.line(depth, format!("func_{}({});", call.callee, args));
+            } else if let Flow::Jump(target) = ins.flow() {
+                let last = lp.is_some_and(|(head, exit)| {
+                    ins.next() == exit && matches!(target, Target::Static(t) if t == head)
+                });
+                if !last {
+                    let jump = self.jump(target, lp);
+                    self.line(depth, jump);
+                }
+            } else {
+                let statement = self.statement(&ins);
+                self.line(depth, statement);
+            }
+            pc = ins.next();
+        }
+    }
+
+    fn prepare(&mut self, entry: usize) {
+        for (addr, call) in self.calls {
+            if !self.body.contains_key(addr) {
+                continue;
+            }
+            let mut store = addr - 4;
+            self.skip.insert(store);
+            for _ in &call.args {
+                store -= 4;
+                self.skip.insert(store);
+            }
+        }
+        if entry == 0 {
+            return;
+        }
+        if let Some(Param::Immediate(size)) = self
+            .body
+            .get(&entry)
+            .filter(|ins| ins.op == 9)
+            .map(|ins| ins.params[0])
+        {
+            self.frame = Some(size);
+            self.skip.insert(entry);
+            for ins in self.body.values() {
+                if ins.op == 9 && ins.params[0] == Param::Immediate(-size) {
+                    let returns = decode(self.program, ins.next()).is_some_and(|next| {
+                        matches!(next.flow(), Flow::Jump(Target::Dynamic(Param::Relative(_))))
+                    });
+                    if returns {
+                        self.skip.insert(ins.addr);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn decompile(program: &[i64]) -> String {
+    let mut calls = HashMap::new();
+    let mut bodies = BTreeMap::new();
+    let mut queue = vec![0];
+    while let Some(entry) = queue.pop() {
+        if bodies.contains_key(&entry) {
+            continue;
+        }
+        let body = explore(program, entry, &mut calls);
+        queue.extend(calls.values().map(|call| call.callee));
+        bodies.insert(entry, body);
+    }
+
+    let mut result = vec![];
+    for (&entry, body) in &bodies {
+        let mut function = Function {
+            program,
+            body,
+            calls: &calls,
+            frame: None,
+            skip: HashSet::new(),
+            labels: BTreeSet::new(),
+            lines: vec![],
+        };
+        function.prepare(entry);
+        function.emit(entry, usize::MAX, 1, None);
+        // Gotos found during the first pass become labels in the second one.
+        function.lines.clear();
+        function.emit(entry, usize::MAX, 1, None);
+
+        let name = if entry == 0 {
+            "main".to_string()
+        } else {
+            format!("func_{}", entry)
+        };
+        // Arguments land just above the return address, so the callee sees
+        // them at the same slots its caller filled.
+        let params = calls
+            .iter()
+            .filter(|(_, call)| call.callee == entry)
+            .max_by_key(|&(&addr, call)| (call.args.len(), std::cmp::Reverse(addr)))
+            .map(|(_, call)| {
+                (1..=call.args.len() as i64)
+                    .map(|k| {
+                        function
+                            .operand(Param::Relative(call.slot + k - function.frame.unwrap_or(0)))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default();
+        result.push(format!("fn {}({}) {{", name, params));
+        result.extend(function.lines);
+        result.push("}".to_string());
+        result.push(String::new());
+    }
+
+    let covered = bodies
+        .values()
+        .flat_map(|body| body.values())
+        .flat_map(|ins| ins.addr..ins.next())
+        .collect::<BTreeSet<_>>();
+    let mut addr = 0;
+    while addr < program.len() {
+        if covered.contains(&addr) {
+            addr += 1;
+            continue;
+        }
+        let start = addr;
+        while addr < program.len() && !covered.contains(&addr) {
+            addr += 1;
+        }
+        result.push(format!(
+            "// data {}..{}: {:?}",
+            start,
+            addr,
+            &program[start..addr]
+        ));
+    }
+
+    result.join("\n").trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    #[test]
+    fn test_decompile_straight_line() {
+        assert_eq!(
+            decompile(&[3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8]),
+            "fn main() {
+    mem[9] = input();
+    mem[9] = (mem[9] == mem[10]) as i64;
+    output(mem[9]);
+    halt();
+}
+
+// data 9..11: [-1, 8]"
+        );
+    }
+
+    #[test]
+    fn test_decompile_loops_and_conditionals() {
+        let program = [
+            1101, 5, 0, 20, 1006, 20, 16, 4, 20, 1001, 20, -1, 20, 1105, 1, 4, 99, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    mem[20] = 5;
+    while mem[20] != 0 {
+        output(mem[20]);
+        mem[20] = mem[20] - 1;
+    }
+    halt();
+}
+
+// data 17..21: [0, 0, 0, 0]"
+        );
+
+        let program = [
+            3, 20, 1007, 20, 8, 21, 1005, 21, 15, 104, 1, 1105, 1, 17, 0, 104, 2, 99, 0, 0, 0, 0,
+        ];
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    mem[20] = input();
+    if mem[20] >= 8 {
+        output(1);
+    } else {
+        output(2);
+    }
+    halt();
+}
+
+// data 14..15: [0]
+// data 18..22: [0, 0, 0, 0]"
+        );
+    }
+
+    #[test]
+    fn test_decompile_calls() {
+        let program = [
+            109, 100, 21101, 7, 0, 1, 21101, 13, 0, 0, 1105, 1, 14, 99, 109, 2, 204, -1, 109, -2,
+            2105, 1, 0,
+        ];
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    rb += 100;
+    func_14(7);
+    halt();
+}
+
+fn func_14(local1) {
+    output(local1);
+    return;
+}"
+        );
+
+        let program = [
+            109, 100, 21101, 7, 0, 2, 21101, 8, 0, 3, 21101, 17, 0, 1, 1105, 1, 18, 99, 109, 4,
+            22201, -2, -1, -2, 204, -2, 109, -4, 2105, 1, 1,
+        ];
+        assert_eq!(
+            decompile(&program),
+            "fn main() {
+    rb += 100;
+    func_18(7, 8);
+    halt();
+}
+
+fn func_18(local2, local3) {
+    local2 = local2 + local3;
+    output(local2);
+    return;
+}"
+        );
+    }
+
+    #[test]
+    fn test_decompile_overlapping_code() {
+        assert!(decompile(&[9, 11, 1005, 9, 3, 3, -3, 0, 1101]).contains("goto label_3;"));
+        let mut rng = StdRng::seed_from_u64(7);
+        let words = [1005, 1006, 1105, 1106, 1101, 7, 8, 9, 99, 0, 3, -3];
+        for _ in 0..2000 {
+            let image = (0..40)
+                .map(|_| words[rng.gen_range(0..words.len())])
+                .collect::<Vec<_>>();
+            decompile(&image);
+        }
+    }
+}
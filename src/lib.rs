@@ -18,6 +18,9 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+pub mod decompiler;
+pub mod ocr;
+pub mod recorder;
 
 pub fn read_input(day: i32) -> String {
     fs::read_to_string(format!("inputs/day{}.txt", day)).unwrap()
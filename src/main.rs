@@ -92,16 +92,14 @@ fn main() {
 
     day = 8;
     let input = adventofcode2019::read_input(day);
-    println!(
-        "Day {} part 1: {}",
-        day,
-        adventofcode2019::day8::part1(&input)
-    );
-    println!(
-        "Day {} part 2:\n{}",
-        day,
-        adventofcode2019::day8::part2(&input)
-    );
+    match adventofcode2019::day8::part1(&input) {
+        Ok(value) => println!("Day {} part 1: {}", day, value),
+        Err(err) => println!("Day {} part 1 failed: {}", day, err),
+    }
+    match adventofcode2019::day8::part2(&input) {
+        Ok(text) => println!("Day {} part 2: {}", day, text),
+        Err(err) => println!("Day {} part 2 failed: {}", day, err),
+    }
 
     day = 9;
     let input = adventofcode2019::read_input(day);
@@ -136,11 +134,10 @@ fn main() {
         day,
         adventofcode2019::day11::part1(&input)
     );
-    println!(
-        "Day {} part 2:\n{}",
-        day,
-        adventofcode2019::day11::part2(&input)
-    );
+    match adventofcode2019::day11::part2(&input) {
+        Ok(text) => println!("Day {} part 2: {}", day, text),
+        Err(err) => println!("Day {} part 2 failed: {}", day, err),
+    }
 
     day = 12;
     let input = adventofcode2019::read_input(day);
@@ -0,0 +1,208 @@
+use std::fmt::Display;
+
+const SMALL: [(char, &str); 16] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+const LARGE: [(char, &str); 15] = [
+    (
+        'A',
+        "..##...#..#.#....##....##....########....##....##....##....#",
+    ),
+    (
+        'B',
+        "#####.#....##....##....######.#....##....##....##....######.",
+    ),
+    (
+        'C',
+        ".####.#....##.....#.....#.....#.....#.....#.....#....#.####.",
+    ),
+    (
+        'E',
+        "#######.....#.....#.....#####.#.....#.....#.....#.....######",
+    ),
+    (
+        'F',
+        "#######.....#.....#.....#####.#.....#.....#.....#.....#.....",
+    ),
+    (
+        'G',
+        ".####.#....##.....#.....#.....#..####....##....##...##.###.#",
+    ),
+    (
+        'H',
+        "#....##....##....##....########....##....##....##....##....#",
+    ),
+    (
+        'J',
+        "...###....#.....#.....#.....#.....#.....#.#...#.#...#..###..",
+    ),
+    (
+        'K',
+        "#....##...#.#..#..#.#...##....##....#.#...#..#..#...#.#....#",
+    ),
+    (
+        'L',
+        "#.....#.....#.....#.....#.....#.....#.....#.....#.....######",
+    ),
+    (
+        'N',
+        "#....###...###...##.#..##.#..##..#.##..#.##...###...###....#",
+    ),
+    (
+        'P',
+        "#####.#....##....##....######.#.....#.....#.....#.....#.....",
+    ),
+    (
+        'R',
+        "#####.#....##....##....######.#..#..#...#.#...#.#....##....#",
+    ),
+    (
+        'X',
+        "#....##....#.#..#..#..#...##....##...#..#..#..#.#....##....#",
+    ),
+    (
+        'Z',
+        "######.....#.....#....#....#....#....#....#.....#.....######",
+    ),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    UnsupportedHeight(usize),
+    UnknownGlyph { column: usize, glyph: String },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight(height) => {
+                write!(f, "unsupported glyph height {}", height)
+            }
+            OcrError::UnknownGlyph { column, glyph } => {
+                write!(f, "unknown glyph at column {}:\n{}", column, glyph)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+pub fn recognize(image: &str) -> Result<String, OcrError> {
+    let rows = image
+        .lines()
+        .map(|line| line.chars().map(|ch| ch == '#').collect::<Vec<_>>())
+        .skip_while(|row| !row.contains(&true))
+        .collect::<Vec<_>>();
+    let height = rows
+        .iter()
+        .rposition(|row| row.contains(&true))
+        .map_or(0, |last| last + 1);
+    let rows = &rows[..height];
+    let (font, width) = match height {
+        6 => (&SMALL[..], 4),
+        10 => (&LARGE[..], 6),
+        _ => return Err(OcrError::UnsupportedHeight(height)),
+    };
+
+    let lit = |x: usize, y: usize| rows[y].get(x).copied().unwrap_or(false);
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut result = String::new();
+    let mut x = 0;
+    while x < columns {
+        if (0..height).all(|y| !lit(x, y)) {
+            x += 1;
+            continue;
+        }
+        let glyph = (0..height)
+            .flat_map(|y| (x..x + width).map(move |x| (x, y)))
+            .map(|(x, y)| if lit(x, y) { '#' } else { '.' })
+            .collect::<String>();
+        match font.iter().find(|(_, pattern)| *pattern == glyph) {
+            Some((letter, _)) => result.push(*letter),
+            None => {
+                let glyph = glyph
+                    .as_bytes()
+                    .chunks(width)
+                    .map(|row| std::str::from_utf8(row).unwrap())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return Err(OcrError::UnknownGlyph { column: x, glyph });
+            }
+        }
+        x += width;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(font: &[(char, &str)], width: usize, text: &str, spacing: usize) -> String {
+        let height = font[0].1.len() / width;
+        (0..height)
+            .map(|y| {
+                text.chars()
+                    .map(|ch| {
+                        let pattern = font.iter().find(|(letter, _)| *letter == ch).unwrap().1;
+                        pattern[y * width..(y + 1) * width].replace('.', " ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(&" ".repeat(spacing))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_recognize_small() {
+        let letters = SMALL.iter().map(|(letter, _)| *letter).collect::<String>();
+        assert_eq!(recognize(&render(&SMALL, 4, &letters, 1)), Ok(letters));
+        let padded = format!(
+            "\n {}\n",
+            render(&SMALL, 4, "CFLUL", 1).replace('\n', "\n ")
+        );
+        assert_eq!(recognize(&padded), Ok("CFLUL".to_string()));
+    }
+
+    #[test]
+    fn test_recognize_large() {
+        let letters = LARGE.iter().map(|(letter, _)| *letter).collect::<String>();
+        assert_eq!(recognize(&render(&LARGE, 6, &letters, 2)), Ok(letters));
+    }
+
+    #[test]
+    fn test_recognize_errors() {
+        let glyph = ".##.\n#..#\n#..#\n##.#\n#..#\n#..#";
+        assert_eq!(
+            recognize(glyph),
+            Err(OcrError::UnknownGlyph {
+                column: 0,
+                glyph: glyph.to_string()
+            })
+        );
+        let image = render(&SMALL, 4, "AB", 1);
+        let broken = image.replacen("###", "#.#", 1);
+        assert!(matches!(
+            recognize(&broken),
+            Err(OcrError::UnknownGlyph { column: 5, .. })
+        ));
+        assert_eq!(recognize("#\n#"), Err(OcrError::UnsupportedHeight(2)));
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+#[derive(Debug, Clone)]
+pub struct FrameRecorder {
+    palette: Vec<[u8; 3]>,
+    scale: usize,
+    delay_ms: u32,
+    frames: Vec<Vec<Vec<u8>>>,
+}
+
+impl FrameRecorder {
+    pub fn new(palette: &[[u8; 3]], scale: usize) -> Self {
+        assert!(
+            !palette.is_empty() && palette.len() <= 256,
+            "palette must have 1 to 256 colors"
+        );
+        Self {
+            palette: palette.to_vec(),
+            scale: scale.max(1),
+            delay_ms: 50,
+            frames: vec![],
+        }
+    }
+
+    pub fn with_delay(mut self, delay_ms: u32) -> Self {
+        self.delay_ms = delay_ms;
+        self
+    }
+
+    pub fn capture(&mut self, tiles: &[Vec<u8>]) {
+        self.frames.push(tiles.to_vec());
+    }
+
+    pub fn frames(&self) -> &[Vec<Vec<u8>>] {
+        &self.frames
+    }
+
+    fn size(&self) -> (usize, usize) {
+        let width = self
+            .frames
+            .iter()
+            .flatten()
+            .map(|row| row.len())
+            .max()
+            .unwrap_or(0);
+        let height = self
+            .frames
+            .iter()
+            .map(|frame| frame.len())
+            .max()
+            .unwrap_or(0);
+        (width * self.scale, height * self.scale)
+    }
+
+    fn pixels(&self, frame: &[Vec<u8>]) -> Vec<u8> {
+        let (width, height) = self.size();
+        let mut pixels = vec![0; width * height];
+        for (y, pixel_row) in pixels.chunks_mut(width.max(1)).enumerate() {
+            let row = frame.get(y / self.scale);
+            for (x, pixel) in pixel_row.iter_mut().enumerate() {
+                let tile = row.and_then(|row| row.get(x / self.scale)).copied();
+                *pixel = tile
+                    .filter(|&t| (t as usize) < self.palette.len())
+                    .unwrap_or(0);
+            }
+        }
+        pixels
+    }
+
+    pub fn write_ppm(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let (width, height) = self.size();
+        let mut manifest = format!(
+            "frames {}\nsize {} {}\ndelay_ms {}\n",
+            self.frames.len(),
+            width,
+            height,
+            self.delay_ms
+        );
+        for (i, frame) in self.frames.iter().enumerate() {
+            let name = format!("frame_{:05}.ppm", i);
+            let mut data = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+            for tile in self.pixels(frame) {
+                data.extend(self.palette[tile as usize]);
+            }
+            fs::write(dir.join(&name), data)?;
+            manifest.push_str(&name);
+            manifest.push('\n');
+        }
+        fs::write(dir.join("manifest.txt"), manifest)
+    }
+
+    pub fn write_gif(&self, writer: &mut impl Write) -> io::Result<()> {
+        let (width, height) = self.size();
+        let bits = (1..=8)
+            .find(|&bits| 1 << bits >= self.palette.len())
+            .unwrap();
+
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&(width as u16).to_le_bytes())?;
+        writer.write_all(&(height as u16).to_le_bytes())?;
+        writer.write_all(&[0xf0 | (bits - 1) as u8, 0, 0])?;
+        for i in 0..1 << bits {
+            writer.write_all(&self.palette.get(i).copied().unwrap_or([0; 3]))?;
+        }
+        writer.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+        let delay = (self.delay_ms / 10) as u16;
+        for frame in &self.frames {
+            writer.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+            writer.write_all(&delay.to_le_bytes())?;
+            writer.write_all(&[0x00, 0x00, 0x2c, 0, 0, 0, 0])?;
+            writer.write_all(&(width as u16).to_le_bytes())?;
+            writer.write_all(&(height as u16).to_le_bytes())?;
+            writer.write_all(&[0x00])?;
+
+            let min_code_size = bits.max(2);
+            writer.write_all(&[min_code_size as u8])?;
+            for block in lzw(&self.pixels(frame), min_code_size).chunks(255) {
+                writer.write_all(&[block.len() as u8])?;
+                writer.write_all(block)?;
+            }
+            writer.write_all(&[0x00])?;
+        }
+        writer.write_all(&[0x3b])
+    }
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: usize) {
+        self.buffer |= (code as u32) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+fn lzw(pixels: &[u8], min_code_size: usize) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut writer = BitWriter {
+        bytes: vec![],
+        buffer: 0,
+        count: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = end + 1;
+    let mut size = min_code_size + 1;
+    writer.write(clear, size);
+
+    let mut iter = pixels.iter();
+    if let Some(&first) = iter.next() {
+        let mut current = first as u16;
+        for &pixel in iter {
+            if let Some(&code) = table.get(&(current, pixel)) {
+                current = code;
+                continue;
+            }
+            writer.write(current, size);
+            if next_code == 4096 {
+                writer.write(clear, size);
+                table.clear();
+                next_code = end + 1;
+                size = min_code_size + 1;
+            } else {
+                table.insert((current, pixel), next_code);
+                if next_code == 1 << size && size < 12 {
+                    size += 1;
+                }
+                next_code += 1;
+            }
+            current = pixel as u16;
+        }
+        writer.write(current, size);
+    }
+    writer.write(end, size);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn unlzw(data: &[u8], min_code_size: usize) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let mut result = vec![];
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut size = min_code_size + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let (mut buffer, mut count, mut bytes) = (0u32, 0, data.iter());
+        loop {
+            while count < size {
+                buffer |= (*bytes.next().unwrap() as u32) << count;
+                count += 8;
+            }
+            let code = (buffer & ((1 << size) - 1)) as usize;
+            buffer >>= size;
+            count -= size;
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).collect();
+                table.extend([vec![], vec![]]);
+                size = min_code_size + 1;
+                prev = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return result;
+            }
+            let entry = match (table.get(code), &prev) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(prev)) => [prev.clone(), vec![prev[0]]].concat(),
+                (None, None) => panic!("invalid code"),
+            };
+            if let Some(prev) = prev {
+                table.push([prev, vec![entry[0]]].concat());
+                if table.len() == 1 << size && size < 12 {
+                    size += 1;
+                }
+            }
+            result.extend(&entry);
+            prev = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_lzw() {
+        let pixels = (0..10000).map(|i| ((i / 7) % 5) as u8).collect::<Vec<_>>();
+        assert_eq!(unlzw(&lzw(&pixels, 3), 3), pixels);
+        let mut rng = StdRng::seed_from_u64(1);
+        let noise = (0..50000)
+            .map(|_| rng.gen_range(0..16))
+            .collect::<Vec<u8>>();
+        assert_eq!(unlzw(&lzw(&noise, 4), 4), noise);
+        assert_eq!(unlzw(&lzw(&[], 2), 2), vec![]);
+    }
+
+    #[test]
+    fn test_write_gif() {
+        let mut recorder = FrameRecorder::new(&[[0, 0, 0], [255, 255, 255], [255, 0, 0]], 2);
+        recorder.capture(&[vec![0, 1], vec![2, 0]]);
+        recorder.capture(&[vec![1, 0, 2]]);
+        let mut data = vec![];
+        recorder.write_gif(&mut data).unwrap();
+        assert_eq!(&data[..6], b"GIF89a");
+        assert_eq!(&data[6..10], &[6, 0, 4, 0]);
+        assert_eq!(data[10], 0xf1);
+        assert_eq!(&data[13..25], &[0, 0, 0, 255, 255, 255, 255, 0, 0, 0, 0, 0]);
+        assert_eq!(data.last(), Some(&0x3b));
+        assert_eq!(
+            recorder.pixels(&recorder.frames()[1])[..6],
+            [1, 1, 0, 0, 2, 2]
+        );
+    }
+
+    #[test]
+    fn test_write_ppm() {
+        let dir = std::env::temp_dir().join(format!("recorder_ppm_{}", std::process::id()));
+        let mut recorder = FrameRecorder::new(&[[0, 0, 0], [10, 20, 30]], 1).with_delay(100);
+        recorder.capture(&[vec![0, 1]]);
+        recorder.write_ppm(&dir).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("manifest.txt")).unwrap(),
+            "frames 1\nsize 2 1\ndelay_ms 100\nframe_00000.ppm\n"
+        );
+        assert_eq!(
+            fs::read(dir.join("frame_00000.ppm")).unwrap(),
+            b"P6\n2 1\n255\n\x00\x00\x00\x0a\x14\x1e"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}